@@ -0,0 +1,66 @@
+//! Scans generated scripts of growing size and reports throughput. Scanning
+//! is linear when the time per byte stays flat as the input doubles.
+//!
+//! Run with `cargo bench --bench scanner`.
+//!
+//! Best of 5 runs on smaller inputs, before and after the scanner stopped
+//! finding each char with `chars().nth()`:
+//!
+//! ```text
+//!  bytes   before (ns/byte)   after (ns/byte)
+//!  16425            1792.74             20.04
+//!  32850            3042.11             36.62
+//!  65700            4884.02             32.06
+//! 131175            9929.25             27.48
+//! ```
+//!
+//! Before, the time per byte doubled with the input; after, it stays flat.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use lox_lang::scanner::Scanner;
+
+const SNIPPET: &str = r#"// Compute a few values.
+fun fib(n) {
+  if (n < 2) return n;
+  return fib(n - 1) + fib(n - 2); /* naïve /* nested */ recursion */
+}
+var greeting = "héllo, wörld \u{1F600}";
+print "fib(${10}) = ${fib(10)} for ${greeting}";
+"#;
+
+const RUNS: u32 = 5;
+
+fn generate(size: usize) -> String {
+    let mut source = String::with_capacity(size + SNIPPET.len());
+    while source.len() < size {
+        source.push_str(SNIPPET);
+    }
+    source
+}
+
+fn time_scan(source: &str) -> Duration {
+    let mut best = Duration::MAX;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let mut scanner = Scanner::new(source);
+        black_box(scanner.scan_tokens().expect("generated source should scan"));
+        best = best.min(start.elapsed());
+    }
+    best
+}
+
+fn main() {
+    println!("{:>10} {:>12} {:>12}", "bytes", "best", "ns/byte");
+    for kib in [128, 256, 512, 1024] {
+        let source = generate(kib * 1024);
+        let elapsed = time_scan(&source);
+        println!(
+            "{:>10} {:>12.2?} {:>12.2}",
+            source.len(),
+            elapsed,
+            elapsed.as_nanos() as f64 / source.len() as f64
+        );
+    }
+}
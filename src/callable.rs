@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::rc::Rc;
 
 use crate::environment::Environment;
+use crate::error::{ErrorCode, LoxError};
 use crate::expression::LiteralValue;
-use crate::interpreter::Interpreter;
+use crate::interpreter::{ControlFlow, Interpreter};
 use crate::scanner::Token;
 use crate::statement::Stmt;
 
@@ -11,8 +13,9 @@ pub enum LoxCallable {
     LoxFunction {
         name: String,
         parameters: Vec<Token>,
-        body: Vec<Stmt>,
-        closure: Environment,
+        body: Rc<Vec<Stmt>>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
     },
     NativeFunction {
         name: String,
@@ -21,7 +24,10 @@ pub enum LoxCallable {
     },
 }
 
-pub type CallableFunction = fn(&Interpreter, &[LiteralValue]) -> Result<LiteralValue, String>;
+/// Why a native function failed: the error code to report and a message.
+pub type NativeError = (ErrorCode, String);
+
+pub type CallableFunction = fn(&Interpreter, &[LiteralValue]) -> Result<LiteralValue, NativeError>;
 
 impl LoxCallable {
     pub fn arity(&self) -> usize {
@@ -31,44 +37,71 @@ impl LoxCallable {
         }
     }
 
+    /// Calls this function with already evaluated `arguments`. `paren` is the
+    /// closing parenthesis of the call expression, used to locate errors.
     pub fn call(
         &self,
         interpreter: &mut Interpreter,
+        paren: &Token,
         arguments: &[LiteralValue],
-    ) -> Result<LiteralValue, String> {
+    ) -> Result<LiteralValue, LoxError> {
         match self {
             Self::LoxFunction {
                 name: _,
                 parameters,
                 body,
                 closure,
+                is_initializer,
             } => {
-                let args_env: HashMap<_, _> = parameters
-                    .iter()
-                    .zip(arguments.iter())
-                    .map(|(param, arg)| (param.lexeme.clone(), arg.clone()))
-                    .collect();
+                let mut env = Environment::with_enclosing(closure.clone());
+                for (param, arg) in parameters.iter().zip(arguments.iter()) {
+                    env.define(param.clone(), arg.clone());
+                }
 
-                let saved_env = interpreter.environment.clone();
-                let saved_return_value = interpreter.return_value.clone();
+                let flow = interpreter.execute_block(body, Rc::new(RefCell::new(env)))?;
+
+                if *is_initializer {
+                    return closure.borrow().get("this").ok_or_else(|| {
+                        LoxError::runtime(
+                            ErrorCode::NotAnInstance,
+                            paren,
+                            "Initializer is not bound to an instance.",
+                        )
+                    });
+                }
 
-                let mut env = closure.clone();
-                env.values.extend(saved_env.values.clone());
-                env.values.extend(args_env);
+                match flow {
+                    ControlFlow::Return(val) => Ok(val),
+                    _ => Ok(LiteralValue::Nil),
+                }
+            }
+            Self::NativeFunction { fun, .. } => fun(interpreter, arguments)
+                .map_err(|(code, msg)| LoxError::runtime(code, paren, &msg)),
+        }
+    }
 
-                let env = env;
-                interpreter.environment = env;
-                interpreter.interpret(body)?;
-                let return_value = interpreter.return_value.clone();
+    /// Returns a copy of this method whose closure defines `this` as `instance`.
+    pub fn bind(&self, instance: LiteralValue) -> LoxCallable {
+        match self {
+            Self::LoxFunction {
+                name,
+                parameters,
+                body,
+                closure,
+                is_initializer,
+            } => {
+                let mut this_env = Environment::with_enclosing(closure.clone());
+                this_env.define(Token::global("this"), instance);
 
-                interpreter.environment = saved_env;
-                interpreter.return_value = saved_return_value;
-                match return_value {
-                    Some(val) => Ok(val),
-                    None => Ok(LiteralValue::Nil),
+                Self::LoxFunction {
+                    name: name.clone(),
+                    parameters: parameters.clone(),
+                    body: body.clone(),
+                    closure: Rc::new(RefCell::new(this_env)),
+                    is_initializer: *is_initializer,
                 }
             }
-            Self::NativeFunction { fun, .. } => fun(interpreter, arguments),
+            Self::NativeFunction { .. } => self.clone(),
         }
     }
 
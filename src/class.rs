@@ -0,0 +1,123 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::callable::LoxCallable;
+use crate::error::{ErrorCode, LoxError};
+use crate::expression::LiteralValue;
+use crate::interpreter::Interpreter;
+use crate::scanner::Token;
+
+#[derive(Clone)]
+pub struct LoxClass {
+    pub name: String,
+    pub superclass: Option<Rc<LoxClass>>,
+    pub methods: HashMap<String, LoxCallable>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, LoxCallable>,
+    ) -> Self {
+        Self {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks up a method on this class, falling back to the superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<LoxCallable> {
+        match (self.methods.get(name), &self.superclass) {
+            (Some(method), _) => Some(method.clone()),
+            (None, Some(superclass)) => superclass.find_method(name),
+            (None, None) => None,
+        }
+    }
+
+    pub fn arity(&self) -> usize {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+
+    /// Creates a new instance of `class`, running its `init` method if it has one.
+    pub fn instantiate(
+        class: &Rc<LoxClass>,
+        interpreter: &mut Interpreter,
+        paren: &Token,
+        arguments: &[LiteralValue],
+    ) -> Result<LiteralValue, LoxError> {
+        let instance =
+            LiteralValue::Instance(Rc::new(RefCell::new(LoxInstance::new(class.clone()))));
+
+        if let Some(initializer) = class.find_method("init") {
+            initializer
+                .bind(instance.clone())
+                .call(interpreter, paren, arguments)?;
+        }
+
+        Ok(instance)
+    }
+}
+
+impl std::fmt::Display for LoxClass {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: HashMap<String, LiteralValue>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        Self {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a property on `instance`, preferring fields over methods.
+    /// Methods are bound to `instance` so `this` resolves inside them.
+    pub fn get(
+        instance: &Rc<RefCell<LoxInstance>>,
+        name: &Token,
+    ) -> Result<LiteralValue, LoxError> {
+        if let Some(value) = instance.borrow().fields.get(&name.lexeme) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(&name.lexeme);
+        match method {
+            Some(method) => Ok(LiteralValue::Callable(
+                method.bind(LiteralValue::Instance(instance.clone())),
+            )),
+            None => Err(LoxError::runtime(
+                ErrorCode::UndefinedProperty,
+                name,
+                &format!("Undefined property '{}'.", name.lexeme),
+            )),
+        }
+    }
+
+    /// Looks up a field, ignoring methods.
+    pub fn field(&self, name: &str) -> Option<LiteralValue> {
+        self.fields.get(name).cloned()
+    }
+
+    pub fn set(&mut self, name: &Token, value: LiteralValue) {
+        self.fields.insert(name.lexeme.clone(), value);
+    }
+}
+
+impl std::fmt::Display for LoxInstance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
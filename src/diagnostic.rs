@@ -0,0 +1,209 @@
+use crate::error::LoxError;
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const CYAN: &str = "\x1b[1;36m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+const REPEATED_FRAMES: usize = 3;
+
+/// Renders errors in the style of rustc: a header with the error code, the
+/// location, the offending source line with the span underlined, and any
+/// `help:` notes. ANSI colors are only emitted when `color` is set.
+pub struct Renderer<'a> {
+    source: &'a str,
+    color: bool,
+}
+
+impl<'a> Renderer<'a> {
+    pub fn new(source: &'a str, color: bool) -> Self {
+        Self { source, color }
+    }
+
+    /// Renders every error in one report, separated by blank lines and
+    /// followed by a summary when there is more than one.
+    pub fn render_all(&self, errors: &[LoxError]) -> String {
+        let mut rendered: Vec<String> = errors.iter().map(|err| self.render(err)).collect();
+
+        if errors.len() > 1 {
+            rendered.push(format!(
+                "{}: aborting due to {} previous errors",
+                self.paint(RED, "error"),
+                errors.len()
+            ));
+        }
+
+        rendered.join("\n\n")
+    }
+
+    pub fn render(&self, err: &LoxError) -> String {
+        let details = err.details();
+        let mut out = format!(
+            "{}{}",
+            self.paint(RED, &format!("error[{}]", details.code)),
+            self.paint(BOLD, &format!(": {}", details.message))
+        );
+
+        let location = match &details.file {
+            Some(file) => format!("{}:{}:{}", file, err.line(), err.column()),
+            None => format!("{}:{}", err.line(), err.column()),
+        };
+
+        match self.source_line(err.line()) {
+            Some(line) => {
+                let number = err.line().to_string();
+                let pad = " ".repeat(number.len());
+                let gutter = self.paint(BLUE, "|");
+
+                out.push_str(&format!("\n{pad}{} {location}", self.paint(BLUE, "-->")));
+                out.push_str(&format!("\n{pad} {gutter}"));
+                out.push_str(&format!("\n{} {gutter} {line}", self.paint(BLUE, &number)));
+                out.push_str(&format!(
+                    "\n{pad} {gutter} {}{}",
+                    self.indent(line, err.column()),
+                    self.paint(RED, &self.underline(line, err))
+                ));
+                for note in &details.help {
+                    out.push_str(&format!(
+                        "\n{pad} {} {}: {note}",
+                        self.paint(BLUE, "="),
+                        self.paint(CYAN, "help")
+                    ));
+                }
+                out.push_str(&self.trace(&pad, err));
+            }
+            None => {
+                out.push_str(&format!("\n {} {location}", self.paint(BLUE, "-->")));
+                for note in &details.help {
+                    out.push_str(&format!(
+                        "\n {} {}: {note}",
+                        self.paint(BLUE, "="),
+                        self.paint(CYAN, "help")
+                    ));
+                }
+                out.push_str(&self.trace("", err));
+            }
+        }
+
+        out
+    }
+
+    /// The calls that were active when a runtime error was raised. Runs of
+    /// the same frame, as left by deep recursion, are cut short after
+    /// `REPEATED_FRAMES` lines.
+    fn trace(&self, pad: &str, err: &LoxError) -> String {
+        if err.trace().is_empty() {
+            return String::new();
+        }
+
+        let mut out = format!(
+            "\n{pad} {} {}: stack trace, most recent call first:",
+            self.paint(BLUE, "="),
+            self.paint(BOLD, "note")
+        );
+        let trace = err.trace();
+        let mut i = 0;
+        while i < trace.len() {
+            let run = trace[i..].iter().take_while(|f| **f == trace[i]).count();
+            for frame in &trace[i..i + run.min(REPEATED_FRAMES)] {
+                out.push_str(&format!("\n{pad}     {frame}"));
+            }
+            if run > REPEATED_FRAMES {
+                out.push_str(&format!(
+                    "\n{pad}     ... the frame above repeats {} more times",
+                    run - REPEATED_FRAMES
+                ));
+            }
+            i += run;
+        }
+        out
+    }
+
+    fn source_line(&self, line: usize) -> Option<&'a str> {
+        if line == 0 {
+            return None;
+        }
+        self.source.lines().nth(line - 1)
+    }
+
+    /// Whitespace that lines the caret up with `column`, keeping tabs so the
+    /// caret stays aligned however the terminal renders them.
+    fn indent(&self, line: &str, column: usize) -> String {
+        line.chars()
+            .take(column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect()
+    }
+
+    /// Carets under the span, clipped to the end of its first line.
+    fn underline(&self, line: &str, err: &LoxError) -> String {
+        let range = err.range();
+        let text = self.source.get(range).unwrap_or("");
+        let text = text.lines().next().unwrap_or("");
+        let remaining = line.chars().count().saturating_sub(err.column() - 1);
+        let width = text.chars().count().min(remaining).max(1);
+
+        "^".repeat(width)
+    }
+
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.color {
+            format!("{style}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn parse_errors(source: &str) -> Vec<LoxError> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        Parser::new(tokens).parse().unwrap_err()
+    }
+
+    #[test]
+    fn render_snippet_with_caret() {
+        let source = "var a = 1;\nvar b = a +;";
+        let errors = parse_errors(source);
+        let rendered = Renderer::new(source, false).render(&errors[0].clone().with_file("t.lox"));
+
+        assert_eq!(
+            rendered,
+            "error[E0202]: Expected an expression, got Semicolon.\n \
+             --> t.lox:2:12\n  \
+             |\n\
+             2 | var b = a +;\n  \
+             |            ^"
+        );
+    }
+
+    #[test]
+    fn render_help_and_summary() {
+        let source = "1 = 2;\nprint (;";
+        let errors = parse_errors(source);
+        let rendered = Renderer::new(source, false).render_all(&errors);
+
+        assert!(rendered.contains("1 | 1 = 2;\n  |   ^\n  = help:"));
+        assert!(rendered.contains("2 | print (;\n  |        ^"));
+        assert!(rendered.ends_with("error: aborting due to 2 previous errors"));
+    }
+
+    #[test]
+    fn render_color_only_when_requested() {
+        let source = "print @;";
+        let mut scanner = Scanner::new(source);
+        let errors = scanner.scan_tokens().unwrap_err();
+
+        assert!(!Renderer::new(source, false)
+            .render(&errors[0])
+            .contains('\x1b'));
+        assert!(Renderer::new(source, true).render(&errors[0]).contains(RED));
+    }
+}
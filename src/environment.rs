@@ -1,12 +1,21 @@
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 
+use crate::error::{ErrorCode, LoxError};
 use crate::expression::LiteralValue;
 use crate::scanner::Token;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub struct Environment {
-    pub values: HashMap<String, LiteralValue>,
-    pub enclosing: Option<Box<Environment>>,
+    values: HashMap<String, LiteralValue>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Environment {
@@ -17,10 +26,12 @@ impl Environment {
         }
     }
 
-    pub fn with_enclosing(enclosing: Environment) -> Environment {
+    /// Creates a new scope nested in `enclosing`. The enclosing scope is shared,
+    /// so assignments made through either handle are visible to both.
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Environment {
         Self {
             values: HashMap::new(),
-            enclosing: Some(Box::new(enclosing)),
+            enclosing: Some(enclosing),
         }
     }
 
@@ -34,24 +45,62 @@ impl Environment {
 
         match (old_value, &self.enclosing) {
             (Some(val), _) => Some(val.clone()),
-            (_, Some(env)) => env.get(name),
+            (_, Some(env)) => env.borrow().get(name),
             (_, _) => None,
         }
     }
 
-    pub fn assign(&mut self, token: Token, value: &LiteralValue) -> Result<(), String> {
+    /// Gets `name` from the scope exactly `distance` levels up, as computed by
+    /// the resolver.
+    pub fn get_at(&self, distance: usize, name: &str) -> Option<LiteralValue> {
+        if distance == 0 {
+            return self.values.get(name).cloned();
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(distance - 1, name),
+            None => None,
+        }
+    }
+
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        token: Token,
+        value: &LiteralValue,
+    ) -> Result<(), LoxError> {
+        if distance == 0 {
+            self.define(token, value.clone());
+            return Ok(());
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, token, value),
+            None => Err(Self::undefined(&token)),
+        }
+    }
+
+    pub fn assign(&mut self, token: Token, value: &LiteralValue) -> Result<(), LoxError> {
         if self.values.contains_key(&token.lexeme) {
             self.define(token, value.clone());
             return Ok(());
         }
 
-        match &mut self.enclosing {
-            Some(enclosing) => enclosing.assign(token, value),
-            None => Err(format!(
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign(token, value),
+            None => Err(Self::undefined(&token)),
+        }
+    }
+
+    fn undefined(token: &Token) -> LoxError {
+        LoxError::runtime(
+            ErrorCode::UndefinedVariable,
+            token,
+            &format!(
                 "Attempting to assign to variable '{}' that does not exist",
                 token.lexeme
-            )),
-        }
+            ),
+        )
     }
 }
 
@@ -63,4 +112,37 @@ mod tests {
     fn initialize_environment() {
         let _environment = Environment::new();
     }
+
+    #[test]
+    fn assign_through_shared_enclosing() {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+        globals
+            .borrow_mut()
+            .define(Token::global("a"), LiteralValue::Number(1.0));
+
+        let mut first = Environment::with_enclosing(globals.clone());
+        let second = Environment::with_enclosing(globals.clone());
+
+        first
+            .assign(Token::global("a"), &LiteralValue::Number(2.0))
+            .unwrap();
+
+        assert_eq!(second.get("a"), Some(LiteralValue::Number(2.0)));
+        assert_eq!(globals.borrow().get("a"), Some(LiteralValue::Number(2.0)));
+    }
+
+    #[test]
+    fn get_at_skips_shadowing_scopes() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .define(Token::global("a"), LiteralValue::Number(1.0));
+
+        let mut inner = Environment::with_enclosing(outer);
+        inner.define(Token::global("a"), LiteralValue::Number(2.0));
+
+        assert_eq!(inner.get_at(0, "a"), Some(LiteralValue::Number(2.0)));
+        assert_eq!(inner.get_at(1, "a"), Some(LiteralValue::Number(1.0)));
+        assert_eq!(inner.get_at(2, "a"), None);
+    }
 }
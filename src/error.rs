@@ -0,0 +1,293 @@
+use std::ops::Range;
+
+use crate::expression::LiteralValue;
+use crate::scanner::{Span, Token};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ErrorCode {
+    // Scan errors.
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidNumber,
+    UnterminatedComment,
+    InvalidEscape,
+    UnterminatedInterpolation,
+    ConfusableCharacter,
+    MixedScriptIdentifier,
+
+    // Parse errors.
+    ExpectedToken,
+    ExpectedExpression,
+    InvalidAssignmentTarget,
+    TooManyArguments,
+
+    // Resolve errors.
+    DuplicateDeclaration,
+    ReadInOwnInitializer,
+    ReturnOutsideFunction,
+    ReturnValueFromInitializer,
+    InvalidThis,
+    InvalidSuper,
+    SelfInheritance,
+    InvalidLoopControl,
+
+    // Runtime errors.
+    UndefinedVariable,
+    UndefinedProperty,
+    InvalidOperands,
+    NotCallable,
+    ArityMismatch,
+    InvalidSuperclass,
+    NotAnInstance,
+    NativeFunction,
+    NotIndexable,
+    InvalidIndex,
+    IndexOutOfBounds,
+    UnhashableKey,
+    KeyNotFound,
+    NotIterable,
+    UncaughtException,
+    DivisionByZero,
+    IntegerOverflow,
+    StackOverflow,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0101",
+            ErrorCode::UnterminatedString => "E0102",
+            ErrorCode::InvalidNumber => "E0103",
+            ErrorCode::UnterminatedComment => "E0104",
+            ErrorCode::InvalidEscape => "E0105",
+            ErrorCode::UnterminatedInterpolation => "E0106",
+            ErrorCode::ConfusableCharacter => "E0107",
+            ErrorCode::MixedScriptIdentifier => "E0108",
+            ErrorCode::ExpectedToken => "E0201",
+            ErrorCode::ExpectedExpression => "E0202",
+            ErrorCode::InvalidAssignmentTarget => "E0203",
+            ErrorCode::TooManyArguments => "E0204",
+            ErrorCode::DuplicateDeclaration => "E0301",
+            ErrorCode::ReadInOwnInitializer => "E0302",
+            ErrorCode::ReturnOutsideFunction => "E0303",
+            ErrorCode::ReturnValueFromInitializer => "E0304",
+            ErrorCode::InvalidThis => "E0305",
+            ErrorCode::InvalidSuper => "E0306",
+            ErrorCode::SelfInheritance => "E0307",
+            ErrorCode::InvalidLoopControl => "E0308",
+            ErrorCode::UndefinedVariable => "E0401",
+            ErrorCode::UndefinedProperty => "E0402",
+            ErrorCode::InvalidOperands => "E0403",
+            ErrorCode::NotCallable => "E0404",
+            ErrorCode::ArityMismatch => "E0405",
+            ErrorCode::InvalidSuperclass => "E0406",
+            ErrorCode::NotAnInstance => "E0407",
+            ErrorCode::NativeFunction => "E0408",
+            ErrorCode::NotIndexable => "E0409",
+            ErrorCode::InvalidIndex => "E0410",
+            ErrorCode::IndexOutOfBounds => "E0411",
+            ErrorCode::UnhashableKey => "E0412",
+            ErrorCode::KeyNotFound => "E0413",
+            ErrorCode::NotIterable => "E0414",
+            ErrorCode::UncaughtException => "E0415",
+            ErrorCode::DivisionByZero => "E0416",
+            ErrorCode::IntegerOverflow => "E0417",
+            ErrorCode::StackOverflow => "E0418",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub code: ErrorCode,
+    pub message: String,
+    pub file: Option<String>,
+    pub span: Span,
+    pub token: Option<Token>,
+    pub help: Vec<String>,
+    /// Active calls when a runtime error was raised, innermost first.
+    pub trace: Vec<String>,
+    /// The value passed to `throw`, if the error came from a throw statement.
+    pub thrown: Option<LiteralValue>,
+}
+
+/// An error raised by one of the interpreter's stages, carrying enough
+/// location information for tooling to point at the offending source.
+#[derive(Debug, Clone)]
+pub enum LoxError {
+    Scan(Box<ErrorDetails>),
+    Parse(Box<ErrorDetails>),
+    Resolve(Box<ErrorDetails>),
+    Runtime(Box<ErrorDetails>),
+}
+
+impl LoxError {
+    pub fn scan(code: ErrorCode, span: Span, message: &str) -> Self {
+        Self::Scan(Box::new(ErrorDetails {
+            code,
+            message: message.to_string(),
+            file: None,
+            span,
+            token: None,
+            help: vec![],
+            trace: vec![],
+            thrown: None,
+        }))
+    }
+
+    pub fn parse(code: ErrorCode, token: &Token, message: &str) -> Self {
+        Self::Parse(Self::details_at(code, token, message))
+    }
+
+    pub fn resolve(code: ErrorCode, token: &Token, message: &str) -> Self {
+        Self::Resolve(Self::details_at(code, token, message))
+    }
+
+    pub fn runtime(code: ErrorCode, token: &Token, message: &str) -> Self {
+        Self::Runtime(Self::details_at(code, token, message))
+    }
+
+    fn details_at(code: ErrorCode, token: &Token, message: &str) -> Box<ErrorDetails> {
+        Box::new(ErrorDetails {
+            code,
+            message: message.to_string(),
+            file: None,
+            span: token.span,
+            token: Some(token.clone()),
+            help: vec![],
+            trace: vec![],
+            thrown: None,
+        })
+    }
+
+    pub fn details(&self) -> &ErrorDetails {
+        match self {
+            LoxError::Scan(details)
+            | LoxError::Parse(details)
+            | LoxError::Resolve(details)
+            | LoxError::Runtime(details) => details,
+        }
+    }
+
+    fn details_mut(&mut self) -> &mut ErrorDetails {
+        match self {
+            LoxError::Scan(details)
+            | LoxError::Parse(details)
+            | LoxError::Resolve(details)
+            | LoxError::Runtime(details) => details,
+        }
+    }
+
+    pub fn with_file(mut self, file: &str) -> Self {
+        self.details_mut().file = Some(file.to_string());
+        self
+    }
+
+    /// Attaches a `help:` note shown beneath the rendered diagnostic.
+    pub fn with_help(mut self, note: &str) -> Self {
+        self.details_mut().help.push(note.to_string());
+        self
+    }
+
+    /// Attaches a stack trace, unless a deeper call already attached one.
+    pub fn with_trace(mut self, trace: Vec<String>) -> Self {
+        if self.details().trace.is_empty() {
+            self.details_mut().trace = trace;
+        }
+        self
+    }
+
+    /// Attaches the value of a `throw` statement, so `catch` can recover it.
+    pub fn with_thrown(mut self, value: LiteralValue) -> Self {
+        self.details_mut().thrown = Some(value);
+        self
+    }
+
+    pub fn thrown(&self) -> Option<&LiteralValue> {
+        self.details().thrown.as_ref()
+    }
+
+    pub fn trace(&self) -> &[String] {
+        &self.details().trace
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        self.details().code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.details().message
+    }
+
+    pub fn line(&self) -> usize {
+        self.details().span.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.details().span.column
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.details().span.start..self.details().span.end
+    }
+}
+
+impl std::fmt::Display for LoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line(), self.message())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_matches_line_prefixed_message() {
+        let token = Token::global("a");
+        let err = LoxError::runtime(
+            ErrorCode::UndefinedVariable,
+            &token,
+            "Variable 'a' has not been declared.",
+        );
+
+        assert_eq!(
+            err.to_string(),
+            "Line 0: Variable 'a' has not been declared."
+        );
+        assert_eq!(err.code().as_str(), "E0401");
+    }
+
+    #[test]
+    fn scan_error_carries_span() {
+        let span = Span {
+            start: 4,
+            end: 5,
+            line: 2,
+            column: 3,
+        };
+        let err = LoxError::scan(ErrorCode::UnexpectedCharacter, span, "Unexpected '@'.")
+            .with_file("main.lox");
+
+        assert_eq!(err.line(), 2);
+        assert_eq!(err.column(), 3);
+        assert_eq!(err.range(), 4..5);
+        assert_eq!(err.details().file.as_deref(), Some("main.lox"));
+    }
+
+    #[test]
+    fn keep_innermost_trace() {
+        let token = Token::global("f");
+        let err = LoxError::runtime(ErrorCode::InvalidOperands, &token, "Bad operands.")
+            .with_trace(vec!["at f (line 2)".to_string()])
+            .with_trace(vec!["at g (line 5)".to_string()]);
+
+        assert_eq!(err.trace(), ["at f (line 2)"]);
+    }
+}
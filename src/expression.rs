@@ -1,28 +1,39 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::callable::LoxCallable;
-use crate::scanner::{Token, TokenLiteral, TokenType};
+use crate::class::{LoxClass, LoxInstance};
+use crate::map::LoxMap;
+use crate::range::LoxRange;
+use crate::scanner::{Span, Token, TokenLiteral, TokenType};
 use crate::statement::Stmt;
 
 #[derive(Clone)]
 pub enum LiteralValue {
+    Int(i64),
     Number(f64),
     StringValue(String),
     True,
     False,
     Nil,
     Callable(LoxCallable),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<LiteralValue>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Range(LoxRange),
 }
 
 impl LiteralValue {
     pub fn from_token(token: Token) -> Self {
         match token.token_type {
-            TokenType::Number => {
-                let value = match token.literal {
-                    Some(TokenLiteral::FValue(x)) => x,
-                    _ => panic!("Cannot be unwrapped as float"),
-                };
-                Self::Number(value)
-            }
-            TokenType::StringLit => {
+            TokenType::Number => match token.literal {
+                Some(TokenLiteral::IValue(x)) => Self::Int(x),
+                Some(TokenLiteral::FValue(x)) => Self::Number(x),
+                _ => panic!("Cannot be unwrapped as a number"),
+            },
+            TokenType::StringLit | TokenType::Interpolation => {
                 let value = match token.literal {
                     Some(TokenLiteral::StringValue(s)) => s,
                     _ => panic!("Cannot be unwrapped as String"),
@@ -46,30 +57,92 @@ impl LiteralValue {
 
     pub fn to_type(&self) -> &str {
         match self {
-            LiteralValue::Number(_) => "Number",
+            LiteralValue::Int(_) => "Int",
+            LiteralValue::Number(_) => "Float",
             LiteralValue::StringValue(_) => "String",
             LiteralValue::True => "Boolean",
             LiteralValue::False => "Boolean",
             LiteralValue::Nil => "nil",
             LiteralValue::Callable(_) => "Callable",
+            LiteralValue::Class(_) => "Class",
+            LiteralValue::Instance(_) => "Instance",
+            LiteralValue::List(_) => "List",
+            LiteralValue::Map(_) => "Map",
+            LiteralValue::Range(_) => "Range",
+        }
+    }
+
+    /// Formats a value nested inside a collection, quoting strings so
+    /// `["1"]` and `[1]` print differently.
+    pub fn element_string(&self) -> String {
+        self.nested_string(&mut vec![])
+    }
+
+    fn nested_string(&self, visiting: &mut Vec<*const ()>) -> String {
+        match self {
+            LiteralValue::StringValue(s) => format!("{s:?}"),
+            other => other.collection_string(visiting),
+        }
+    }
+
+    /// Formats lists and maps. `visiting` holds the collections that are
+    /// being printed, so one that contains itself prints as `[...]` or
+    /// `{...}` where it repeats.
+    fn collection_string(&self, visiting: &mut Vec<*const ()>) -> String {
+        match self {
+            LiteralValue::List(list) => {
+                let ptr = Rc::as_ptr(list) as *const ();
+                if visiting.contains(&ptr) {
+                    return String::from("[...]");
+                }
+                visiting.push(ptr);
+                let elements: Vec<String> = list
+                    .borrow()
+                    .iter()
+                    .map(|value| value.nested_string(visiting))
+                    .collect();
+                visiting.pop();
+                format!("[{}]", elements.join(", "))
+            }
+            LiteralValue::Map(map) => {
+                let ptr = Rc::as_ptr(map) as *const ();
+                if visiting.contains(&ptr) {
+                    return String::from("{...}");
+                }
+                visiting.push(ptr);
+                let entries: Vec<String> = map
+                    .borrow()
+                    .entries()
+                    .iter()
+                    .map(|(key, value)| {
+                        format!(
+                            "{}: {}",
+                            key.nested_string(visiting),
+                            value.nested_string(visiting)
+                        )
+                    })
+                    .collect();
+                visiting.pop();
+                format!("{{{}}}", entries.join(", "))
+            }
+            other => other.to_string(),
         }
     }
 
     pub fn is_truthy(&self) -> bool {
         match self {
+            LiteralValue::Int(x) => *x != 0,
             LiteralValue::Number(x) => *x != 0.0f64,
             LiteralValue::StringValue(s) => !s.is_empty(),
             LiteralValue::True => true,
             LiteralValue::False => false,
             LiteralValue::Nil => false,
-            LiteralValue::Callable(_) => panic!("Cannot use callable as truthy value"),
-        }
-    }
-
-    pub fn as_callable(&self) -> Option<LoxCallable> {
-        match self {
-            LiteralValue::Callable(callable) => Some(callable.clone()),
-            _ => None,
+            LiteralValue::Callable(_) => true,
+            LiteralValue::Class(_) => true,
+            LiteralValue::Instance(_) => true,
+            LiteralValue::List(list) => !list.borrow().is_empty(),
+            LiteralValue::Map(map) => !map.borrow().is_empty(),
+            LiteralValue::Range(range) => !range.is_empty(),
         }
     }
 }
@@ -77,12 +150,19 @@ impl LiteralValue {
 impl std::fmt::Display for LiteralValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
-            LiteralValue::Number(x) => x.to_string(),
+            LiteralValue::Int(x) => x.to_string(),
+            // Debug formatting keeps the `.0` on whole floats, so `3.0`
+            // prints differently from `3`.
+            LiteralValue::Number(x) => format!("{x:?}"),
             LiteralValue::StringValue(s) => s.clone(),
             LiteralValue::True => String::from("true"),
             LiteralValue::False => String::from("false"),
             LiteralValue::Nil => String::from("nil"),
             LiteralValue::Callable(callable) => callable.to_string(),
+            LiteralValue::Class(class) => class.to_string(),
+            LiteralValue::Instance(instance) => instance.borrow().to_string(),
+            LiteralValue::List(_) | LiteralValue::Map(_) => self.collection_string(&mut vec![]),
+            LiteralValue::Range(range) => range.to_string(),
         };
         write!(f, "{s}")
     }
@@ -96,8 +176,20 @@ impl std::fmt::Debug for LiteralValue {
 
 impl PartialEq for LiteralValue {
     fn eq(&self, other: &Self) -> bool {
+        self.equals(other, &mut vec![])
+    }
+}
+
+impl LiteralValue {
+    /// Compares by value. `comparing` holds the pairs of collections whose
+    /// comparison is in progress; meeting a pair again means both sides
+    /// refer back to themselves at the same place, so that place is equal.
+    fn equals(&self, other: &Self, comparing: &mut Vec<(*const (), *const ())>) -> bool {
         match (self, other) {
+            (LiteralValue::Int(x), LiteralValue::Int(y)) => x == y,
             (LiteralValue::Number(x), LiteralValue::Number(y)) => x == y,
+            (LiteralValue::Int(i), LiteralValue::Number(x))
+            | (LiteralValue::Number(x), LiteralValue::Int(i)) => float_to_int(*x) == Some(*i),
             (LiteralValue::StringValue(s1), LiteralValue::StringValue(s2)) => s1 == s2,
             (LiteralValue::True, LiteralValue::True) => true,
             (LiteralValue::False, LiteralValue::False) => true,
@@ -105,62 +197,229 @@ impl PartialEq for LiteralValue {
             (LiteralValue::Callable(c1), LiteralValue::Callable(c2)) => {
                 c1.name() == c2.name() && c1.arity() == c2.arity()
             }
+            (LiteralValue::Class(c1), LiteralValue::Class(c2)) => Rc::ptr_eq(c1, c2),
+            (LiteralValue::Instance(i1), LiteralValue::Instance(i2)) => Rc::ptr_eq(i1, i2),
+            (LiteralValue::Range(r1), LiteralValue::Range(r2)) => r1 == r2,
+            (LiteralValue::List(l1), LiteralValue::List(l2)) => {
+                let pair = (Rc::as_ptr(l1) as *const (), Rc::as_ptr(l2) as *const ());
+                if Rc::ptr_eq(l1, l2) || comparing.contains(&pair) {
+                    return true;
+                }
+                let (l1, l2) = (l1.borrow(), l2.borrow());
+                comparing.push(pair);
+                let equal = l1.len() == l2.len()
+                    && l1
+                        .iter()
+                        .zip(l2.iter())
+                        .all(|(a, b)| a.equals(b, comparing));
+                comparing.pop();
+                equal
+            }
+            (LiteralValue::Map(m1), LiteralValue::Map(m2)) => {
+                let pair = (Rc::as_ptr(m1) as *const (), Rc::as_ptr(m2) as *const ());
+                if Rc::ptr_eq(m1, m2) || comparing.contains(&pair) {
+                    return true;
+                }
+                let (m1, m2) = (m1.borrow(), m2.borrow());
+                comparing.push(pair);
+                let equal = m1.len() == m2.len()
+                    && m1.entries().iter().all(|(key, value)| match m2.get(key) {
+                        Ok(Some(other)) => value.equals(&other, comparing),
+                        _ => false,
+                    });
+                comparing.pop();
+                equal
+            }
             _ => false,
         }
     }
 }
 
+/// Converts a float to the integer it equals exactly, if there is one.
+pub fn float_to_int(x: f64) -> Option<i64> {
+    // `i64::MAX as f64` rounds up to 2^63, which is out of range.
+    if x.fract() == 0.0 && x >= i64::MIN as f64 && x < i64::MAX as f64 {
+        Some(x as i64)
+    } else {
+        None
+    }
+}
+
+static NEXT_EXPR_ID: AtomicUsize = AtomicUsize::new(0);
+
 #[derive(Debug, Clone)]
 pub enum Expr {
     Assign {
+        id: usize,
         name: Token,
         value: Box<Expr>,
+        span: Span,
     },
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
     },
+    /// A call. `optional` marks `f?.()`, which is nil when `f` is nil.
     Call {
         callee: Box<Expr>,
         paren: Token,
         arguments: Vec<Expr>,
+        optional: bool,
+        span: Span,
+    },
+    /// `cond ? then_expr : else_expr`.
+    Conditional {
+        condition: Box<Expr>,
+        then_expr: Box<Expr>,
+        else_expr: Box<Expr>,
+        span: Span,
+    },
+    /// A property access. `optional` marks `obj?.name`, which is nil when
+    /// `obj` is nil.
+    Get {
+        object: Box<Expr>,
+        name: Token,
+        optional: bool,
+        span: Span,
     },
     Grouping {
         expression: Box<Expr>,
+        span: Span,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        span: Span,
+    },
+    IndexSet {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    /// A string with embedded expressions; `parts` alternate between
+    /// string literals and the expressions to stringify between them.
+    Interpolation {
+        parts: Vec<Expr>,
+        span: Span,
     },
     Lambda {
         paren: Token,
         params: Vec<Token>,
-        body: Vec<Stmt>,
+        body: Rc<Vec<Stmt>>,
+        span: Span,
+    },
+    List {
+        elements: Vec<Expr>,
+        span: Span,
     },
     Literal {
         value: LiteralValue,
+        span: Span,
+    },
+    /// A map literal. `brace` is the opening `{`, used to locate key errors.
+    Map {
+        brace: Token,
+        entries: Vec<(Expr, Expr)>,
+        span: Span,
     },
     Logical {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
+        span: Span,
+    },
+    Set {
+        object: Box<Expr>,
+        name: Token,
+        value: Box<Expr>,
+        span: Span,
+    },
+    Super {
+        id: usize,
+        keyword: Token,
+        method: Token,
+        span: Span,
+    },
+    This {
+        id: usize,
+        keyword: Token,
+        span: Span,
     },
     Unary {
         operator: Token,
         right: Box<Expr>,
+        span: Span,
+    },
+    /// A compound assignment such as `a += b`, or an increment or decrement,
+    /// where `value` is 1. `target` is a variable, property or index
+    /// expression; `postfix` updates evaluate to the old value.
+    Update {
+        target: Box<Expr>,
+        operator: Token,
+        value: Box<Expr>,
+        postfix: bool,
+        span: Span,
     },
     Variable {
+        id: usize,
         name: Token,
+        span: Span,
     },
 }
 
+impl Expr {
+    /// Returns an id that is unique across every expression parsed by this
+    /// process, so resolved scope depths stay valid for closures created in
+    /// earlier REPL lines.
+    pub fn next_id() -> usize {
+        NEXT_EXPR_ID.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Returns the source range this expression was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Assign { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Conditional { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::IndexSet { span, .. }
+            | Expr::Interpolation { span, .. }
+            | Expr::Lambda { span, .. }
+            | Expr::List { span, .. }
+            | Expr::Literal { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Map { span, .. }
+            | Expr::Set { span, .. }
+            | Expr::Super { span, .. }
+            | Expr::This { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Update { span, .. }
+            | Expr::Variable { span, .. } => *span,
+        }
+    }
+}
+
 impl std::fmt::Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
-            Expr::Assign { name, value } => {
+            Expr::Assign {
+                id: _, name, value, ..
+            } => {
                 format!("({name:?} = {value}")
             }
             Expr::Binary {
                 left,
                 right,
                 operator,
+                ..
             } => {
                 let op = operator.lexeme.clone();
                 format!("({op} {left} {right})")
@@ -169,31 +428,106 @@ impl std::fmt::Display for Expr {
                 callee,
                 paren: _,
                 arguments,
-            } => format!("({callee} {arguments:?})"),
-            Expr::Grouping { expression } => format!("(group {expression})"),
+                optional,
+                ..
+            } => match optional {
+                true => format!("({callee}?. {arguments:?})"),
+                false => format!("({callee} {arguments:?})"),
+            },
+            Expr::Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => format!("(?: {condition} {then_expr} {else_expr})"),
+            Expr::Get {
+                object,
+                name,
+                optional,
+                ..
+            } => match optional {
+                true => format!("(get? {object} {})", name.lexeme),
+                false => format!("(get {object} {})", name.lexeme),
+            },
+            Expr::Grouping { expression, .. } => format!("(group {expression})"),
+            Expr::Index { object, index, .. } => format!("(index {object} {index})"),
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => format!("(index-set {object} {index} {value})"),
+            Expr::Interpolation { parts, .. } => {
+                let parts: Vec<String> = parts.iter().map(|part| part.to_string()).collect();
+                format!("(interp {})", parts.join(" "))
+            }
             Expr::Lambda {
                 paren: _,
                 params,
                 body: _,
+                ..
             } => format!("anon/{}", params.len()),
-            Expr::Literal { value } => format!("{value}"),
+            Expr::List { elements, .. } => {
+                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
+                format!("(list {})", elements.join(" "))
+            }
+            Expr::Literal { value, .. } => format!("{value}"),
             Expr::Logical {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 let op = operator.lexeme.clone();
                 format!("({op} {left} {right})")
             }
+            Expr::Map { entries, .. } => {
+                let entries: Vec<String> = entries
+                    .iter()
+                    .map(|(key, value)| format!("({key} {value})"))
+                    .collect();
+                format!("(map {})", entries.join(" "))
+            }
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => format!("(set {object} {} {value})", name.lexeme),
+            Expr::Super {
+                id: _,
+                keyword: _,
+                method,
+                ..
+            } => format!("(super {})", method.lexeme),
+            Expr::This {
+                id: _, keyword: _, ..
+            } => String::from("this"),
             Expr::Unary {
                 operator,
                 right: expression,
+                ..
             } => {
                 let operator_str = operator.lexeme.clone();
                 // let expression_str = (*expression).to_string();
                 format!("({operator_str} {expression})")
             }
-            Expr::Variable { name } => format!("(var {})", name.lexeme),
+            Expr::Update {
+                target,
+                operator,
+                value,
+                postfix,
+                ..
+            } => match operator.token_type {
+                TokenType::PlusPlus | TokenType::MinusMinus if *postfix => {
+                    format!("({target} {})", operator.lexeme)
+                }
+                TokenType::PlusPlus | TokenType::MinusMinus => {
+                    format!("({} {target})", operator.lexeme)
+                }
+                _ => format!("({} {target} {value})", operator.lexeme),
+            },
+            Expr::Variable { id: _, name, .. } => format!("(var {})", name.lexeme),
         };
         write!(f, "{s}")
     }
@@ -209,29 +543,34 @@ mod tests {
             token_type: TokenType::Minus,
             lexeme: String::from("-"),
             literal: None,
-            line: 0,
+            span: Span::default(),
         };
         let onetwothree = Expr::Literal {
-            value: LiteralValue::Number(123.0),
+            value: LiteralValue::Int(123),
+            span: Span::default(),
         };
         let group = Expr::Grouping {
             expression: Box::from(Expr::Literal {
                 value: LiteralValue::Number(45.67),
+                span: Span::default(),
             }),
+            span: Span::default(),
         };
         let multi = Token {
             token_type: TokenType::Star,
             lexeme: String::from("*"),
             literal: None,
-            line: 0,
+            span: Span::default(),
         };
         let ast = Expr::Binary {
             left: Box::from(Expr::Unary {
                 operator: minus_token,
                 right: Box::from(onetwothree),
+                span: Span::default(),
             }),
             operator: multi,
             right: Box::from(group),
+            span: Span::default(),
         };
         let result = ast.to_string();
         assert_eq!(result, "(* (- 123) (group 45.67))")
@@ -263,8 +602,39 @@ mod tests {
         assert!(!LiteralValue::Nil.is_truthy());
     }
 
+    #[test]
+    fn compare_ints_and_floats() {
+        assert_eq!(LiteralValue::Int(1), LiteralValue::Number(1.0));
+        assert_ne!(LiteralValue::Int(1), LiteralValue::Number(1.5));
+        assert_ne!(
+            LiteralValue::Int(i64::MAX),
+            LiteralValue::Number(2f64.powi(63))
+        );
+        assert_eq!(LiteralValue::Int(3).to_string(), "3");
+        assert_eq!(LiteralValue::Number(3.0).to_string(), "3.0");
+    }
+
     #[test]
     fn logical_expr() {
-        assert!(true);
+        let or_token = Token {
+            token_type: TokenType::Or,
+            lexeme: String::from("or"),
+            literal: None,
+            span: Span::default(),
+        };
+        let ast = Expr::Logical {
+            left: Box::from(Expr::Literal {
+                value: LiteralValue::Nil,
+                span: Span::default(),
+            }),
+            operator: or_token,
+            right: Box::from(Expr::Literal {
+                value: LiteralValue::True,
+                span: Span::default(),
+            }),
+            span: Span::default(),
+        };
+
+        assert_eq!(ast.to_string(), "(or nil true)");
     }
 }
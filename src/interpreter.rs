@@ -1,296 +1,1246 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
 use crate::callable::LoxCallable;
+use crate::class::{LoxClass, LoxInstance};
 use crate::environment::Environment;
-use crate::expression::{Expr, LiteralValue};
-use crate::scanner::{Token, TokenType};
+use crate::error::{ErrorCode, LoxError};
+use crate::expression::{float_to_int, Expr, LiteralValue};
+use crate::map::LoxMap;
+use crate::natives;
+use crate::parser::Parser;
+use crate::range::LoxRange;
+use crate::resolver::Resolver;
+use crate::scanner::{Scanner, Token, TokenType};
 use crate::statement::Stmt;
 
+/// The built-in `Error` class, written in Lox so its `init` is an ordinary
+/// method. `catch` receives instances of it for runtime errors, with every
+/// field filled in; scripts can throw their own with `throw Error("...")`.
+const ERROR_CLASS: &str = "
+class Error {
+  init(message) {
+    this.message = message;
+    this.code = nil;
+    this.line = nil;
+    this.trace = [];
+  }
+}";
+
+/// How many calls may be active at once. Deeper recursion is reported as a
+/// runtime error instead of overflowing the interpreter's own stack.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+/// A call that is currently executing, kept to build stack traces.
+#[derive(Debug, Clone)]
+pub struct CallFrame {
+    pub name: String,
+    pub line: usize,
+}
+
+impl std::fmt::Display for CallFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {} (line {})", self.name, self.line)
+    }
+}
+
+/// How a statement finished. Anything but `Normal` unwinds the enclosing
+/// blocks until a loop or function call handles it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlFlow {
+    Normal,
+    Return(LiteralValue),
+    Break,
+    Continue,
+}
+
+/// The state of a `for-in` loop.
+enum LoxIterator {
+    /// Lists are walked by position, so elements pushed during the loop are
+    /// visited too.
+    List(Rc<RefCell<Vec<LiteralValue>>>, usize),
+    /// Map keys and string characters, collected when the loop starts.
+    Items(std::vec::IntoIter<LiteralValue>),
+    /// A `range(...)`, producing the item at `next` on each step.
+    Range { range: LoxRange, next: u64 },
+    /// A bound `next` method, called until it returns nil.
+    Instance(LoxCallable),
+}
+
 pub struct Interpreter {
     pub lambda_counter: usize,
-    pub globals: Environment,
-    pub environment: Environment,
-    pub return_value: Option<LiteralValue>,
+    pub globals: Rc<RefCell<Environment>>,
+    pub environment: Rc<RefCell<Environment>>,
+    locals: HashMap<usize, usize>,
+    frames: Vec<CallFrame>,
+    /// The class of the values `catch` receives for built-in runtime errors.
+    error_class: Rc<LoxClass>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        let mut globals = Environment::new();
-
-        let clock_token = Token::global("clock");
-        globals.define(
-            clock_token.clone(),
-            LiteralValue::Callable(LoxCallable::NativeFunction {
-                name: clock_token.lexeme,
-                arity: 0,
-                fun: |_, _| {
-                    let now = std::time::SystemTime::now()
-                        .duration_since(std::time::SystemTime::UNIX_EPOCH)
-                        .expect("Could not get system time.")
-                        .as_millis();
-
-                    Ok(LiteralValue::Number(now as f64 / 1000.0))
-                },
-            }),
-        );
-
-        Self {
+        let globals = Rc::new(RefCell::new(Environment::new()));
+
+        natives::define_globals(&mut globals.borrow_mut());
+
+        let mut interpreter = Self {
             lambda_counter: 0,
-            globals: Environment::new(),
+            globals: globals.clone(),
             environment: globals,
-            return_value: None,
+            locals: HashMap::new(),
+            frames: vec![],
+            error_class: Rc::new(LoxClass::new(String::from("Error"), None, HashMap::new())),
+        };
+        interpreter.define_error_class();
+        interpreter
+    }
+
+    fn define_error_class(&mut self) {
+        let tokens = Scanner::new(ERROR_CLASS)
+            .scan_tokens()
+            .expect("The Error class should scan.");
+        let statements = Parser::new(tokens)
+            .parse()
+            .expect("The Error class should parse.");
+        let locals = Resolver::new()
+            .resolve(&statements)
+            .expect("The Error class should resolve.");
+        self.resolve(locals);
+        self.interpret(&statements)
+            .expect("The Error class should be defined.");
+
+        match self.globals.borrow().get("Error") {
+            Some(LiteralValue::Class(class)) => self.error_class = class,
+            _ => unreachable!("The Error class was just defined."),
         }
     }
 
-    pub fn evaluate(&mut self, expr: &Expr) -> Result<LiteralValue, String> {
-        match expr {
-            Expr::Assign { name, value } => {
-                let new_value = self.evaluate(value)?;
+    /// Records the scope depths computed by the resolver. Expressions that
+    /// were not resolved are looked up in the global scope.
+    pub fn resolve(&mut self, locals: HashMap<usize, usize>) {
+        self.locals.extend(locals);
+    }
 
-                self.environment.assign(name.clone(), &new_value)?;
+    fn look_up_variable(&self, name: &Token, id: usize) -> Option<LiteralValue> {
+        match self.locals.get(&id) {
+            Some(distance) => self.environment.borrow().get_at(*distance, &name.lexeme),
+            None => self.globals.borrow().get(&name.lexeme),
+        }
+    }
 
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<LiteralValue, LoxError> {
+        match expr {
+            Expr::Assign {
+                id, name, value, ..
+            } => {
+                let new_value = self.evaluate(value)?;
+                self.assign_variable(*id, name, &new_value)?;
                 Ok(new_value)
             }
             Expr::Binary {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 let expr_l = self.evaluate(left)?;
                 let expr_r = self.evaluate(right)?;
 
-                match (&expr_l, operator.token_type, &expr_r) {
-                    (LiteralValue::Number(x), TokenType::Plus, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::Number(x + y))
-                    }
-                    (LiteralValue::Number(x), TokenType::Minus, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::Number(x - y))
-                    }
-                    (LiteralValue::Number(x), TokenType::Star, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::Number(x * y))
-                    }
-                    (LiteralValue::Number(x), TokenType::Slash, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::Number(x / y))
-                    }
-                    (LiteralValue::Number(x), TokenType::Greater, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::from_bool(x > y))
-                    }
-                    (LiteralValue::Number(x), TokenType::GreaterEqual, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::from_bool(x >= y))
-                    }
-                    (LiteralValue::Number(x), TokenType::Less, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::from_bool(x < y))
-                    }
-                    (LiteralValue::Number(x), TokenType::LessEqual, LiteralValue::Number(y)) => {
-                        Ok(LiteralValue::from_bool(x <= y))
-                    }
-                    (LiteralValue::Number(_), tt, LiteralValue::StringValue(_)) => {
-                        Err(format!("{tt} is not supported for String and Number"))
-                    }
-                    (LiteralValue::StringValue(_), tt, LiteralValue::Number(_)) => {
-                        Err(format!("{tt} is not supported for String and Number"))
-                    }
-                    (
-                        LiteralValue::StringValue(s1),
-                        TokenType::Plus,
-                        LiteralValue::StringValue(s2),
-                    ) => Ok(LiteralValue::StringValue(format!("{s1}{s2}"))),
-                    (
-                        LiteralValue::StringValue(s1),
-                        TokenType::Greater,
-                        LiteralValue::StringValue(s2),
-                    ) => Ok(LiteralValue::from_bool(s1 > s2)),
-                    (
-                        LiteralValue::StringValue(s1),
-                        TokenType::GreaterEqual,
-                        LiteralValue::StringValue(s2),
-                    ) => Ok(LiteralValue::from_bool(s1 >= s2)),
-                    (
-                        LiteralValue::StringValue(s1),
-                        TokenType::Less,
-                        LiteralValue::StringValue(s2),
-                    ) => Ok(LiteralValue::from_bool(s1 < s2)),
-                    (
-                        LiteralValue::StringValue(s1),
-                        TokenType::LessEqual,
-                        LiteralValue::StringValue(s2),
-                    ) => Ok(LiteralValue::from_bool(s1 <= s2)),
-                    (x, TokenType::BangEqual, y) => Ok(LiteralValue::from_bool(x != y)),
-                    (x, TokenType::EqualEqual, y) => Ok(LiteralValue::from_bool(x == y)),
-                    (x, tt, y) => Err(format!("{tt} is not supported for {x:?} and {y:?}")),
+                Self::binary(&expr_l, operator.token_type, &expr_r, operator)
+            }
+            Expr::Call { .. } | Expr::Get { .. } | Expr::Index { .. } => {
+                Ok(self.evaluate_chain(expr)?.unwrap_or(LiteralValue::Nil))
+            }
+            Expr::Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                if self.evaluate(condition)?.is_truthy() {
+                    self.evaluate(then_expr)
+                } else {
+                    self.evaluate(else_expr)
                 }
             }
-            Expr::Call {
-                callee,
-                paren: _,
-                arguments,
-            } => self.call(callee, arguments),
-            Expr::Grouping { expression } => self.evaluate(expression),
+            Expr::Grouping { expression, .. } => self.evaluate(expression),
+            Expr::IndexSet {
+                object,
+                bracket,
+                index,
+                value,
+                ..
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let value = self.evaluate(value)?;
+                self.set_index(object, index, value.clone(), bracket)?;
+                Ok(value)
+            }
+            Expr::Interpolation { parts, .. } => {
+                let mut result = String::new();
+                for part in parts {
+                    result.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(LiteralValue::StringValue(result))
+            }
             Expr::Lambda {
                 paren,
                 params,
                 body,
+                span,
             } => {
                 let lambda_sym = self.lambda_name();
                 let name = Token {
                     token_type: TokenType::Identifier,
                     lexeme: lambda_sym,
                     literal: None,
-                    line: paren.line,
+                    span: paren.span,
                 };
 
-                let maybe_err = self.execute(&Stmt::Function {
+                self.execute(&Stmt::Function {
                     name: name.clone(),
                     params: params.clone(),
                     body: body.clone(),
-                });
+                    span: *span,
+                })?;
 
-                match maybe_err {
-                    Ok(_) => self.evaluate(&Expr::Variable { name }),
-                    Err(msg) => Err(msg),
+                match self.environment.borrow().get(&name.lexeme) {
+                    Some(value) => Ok(value),
+                    None => Err(LoxError::runtime(
+                        ErrorCode::UndefinedVariable,
+                        &name,
+                        &format!("Lambda '{}' was not defined.", name.lexeme),
+                    )),
                 }
             }
-            Expr::Literal { value } => Ok(value.clone()),
+            Expr::List { elements, .. } => {
+                let values: Result<Vec<_>, _> = elements
+                    .iter()
+                    .map(|element| self.evaluate(element))
+                    .collect();
+                Ok(LiteralValue::List(Rc::new(RefCell::new(values?))))
+            }
+            Expr::Literal { value, .. } => Ok(value.clone()),
+            Expr::Map { brace, entries, .. } => {
+                let mut map = LoxMap::new();
+                for (key, value) in entries {
+                    let key = self.evaluate(key)?;
+                    let value = self.evaluate(value)?;
+                    map.insert(key, value)
+                        .map_err(|msg| LoxError::runtime(ErrorCode::UnhashableKey, brace, &msg))?;
+                }
+                Ok(LiteralValue::Map(Rc::new(RefCell::new(map))))
+            }
             Expr::Logical {
                 left,
                 operator,
                 right,
+                ..
             } => {
                 let left = self.evaluate(left)?;
 
-                if operator.token_type == TokenType::Or {
-                    if left.is_truthy() {
-                        return Ok(left);
-                    }
-                } else if !left.is_truthy() {
-                    return Ok(left);
+                match operator.token_type {
+                    TokenType::Or if left.is_truthy() => return Ok(left),
+                    TokenType::And if !left.is_truthy() => return Ok(left),
+                    TokenType::QuestionQuestion if left != LiteralValue::Nil => return Ok(left),
+                    _ => (),
                 }
 
                 self.evaluate(right)
             }
-            Expr::Unary { operator, right } => {
+            Expr::Set {
+                object,
+                name,
+                value,
+                ..
+            } => match self.evaluate(object)? {
+                LiteralValue::Instance(instance) => {
+                    let value = self.evaluate(value)?;
+                    instance.borrow_mut().set(name, value.clone());
+                    Ok(value)
+                }
+                _ => Err(LoxError::runtime(
+                    ErrorCode::NotAnInstance,
+                    name,
+                    "Only instances have fields.",
+                )),
+            },
+            Expr::Super {
+                id,
+                keyword,
+                method,
+                ..
+            } => {
+                let distance = self.locals.get(id).copied().unwrap_or_default();
+                let superclass = match self.look_up_variable(keyword, *id) {
+                    Some(LiteralValue::Class(class)) => class,
+                    _ => {
+                        return Err(LoxError::runtime(
+                            ErrorCode::InvalidSuper,
+                            keyword,
+                            "Can't use 'super' outside of a subclass.",
+                        ))
+                    }
+                };
+                let this = distance
+                    .checked_sub(1)
+                    .and_then(|distance| self.environment.borrow().get_at(distance, "this"));
+                let instance = match this {
+                    Some(instance) => instance,
+                    None => {
+                        return Err(LoxError::runtime(
+                            ErrorCode::InvalidSuper,
+                            keyword,
+                            "Can't use 'super' outside of a method.",
+                        ))
+                    }
+                };
+
+                match superclass.find_method(&method.lexeme) {
+                    Some(function) => Ok(LiteralValue::Callable(function.bind(instance))),
+                    None => Err(LoxError::runtime(
+                        ErrorCode::UndefinedProperty,
+                        method,
+                        &format!("Undefined property '{}'.", method.lexeme),
+                    )),
+                }
+            }
+            Expr::This { id, keyword, .. } => match self.look_up_variable(keyword, *id) {
+                Some(value) => Ok(value),
+                None => Err(LoxError::runtime(
+                    ErrorCode::InvalidThis,
+                    keyword,
+                    "Can't use 'this' outside of a class.",
+                )),
+            },
+            Expr::Unary {
+                operator, right, ..
+            } => {
                 let expr = self.evaluate(right)?;
 
                 match (&expr, operator.token_type) {
+                    (LiteralValue::Int(x), TokenType::Minus) => match x.checked_neg() {
+                        Some(x) => Ok(LiteralValue::Int(x)),
+                        None => Err(LoxError::runtime(
+                            ErrorCode::IntegerOverflow,
+                            operator,
+                            &format!("Minus overflowed for {x}."),
+                        )),
+                    },
                     (LiteralValue::Number(x), TokenType::Minus) => Ok(LiteralValue::Number(-x)),
-                    (_, TokenType::Minus) => Err(format!(
-                        "Minus operator not implemented for {}.",
-                        expr.to_type()
+                    (LiteralValue::Int(x), TokenType::Tilde) => Ok(LiteralValue::Int(!x)),
+                    (LiteralValue::Number(x), TokenType::Tilde) => match float_to_int(*x) {
+                        Some(x) => Ok(LiteralValue::Int(!x)),
+                        None => Err(LoxError::runtime(
+                            ErrorCode::InvalidOperands,
+                            operator,
+                            &format!("Tilde needs an integral operand, got {x}."),
+                        )),
+                    },
+                    (_, TokenType::Tilde) => Err(LoxError::runtime(
+                        ErrorCode::InvalidOperands,
+                        operator,
+                        &format!("Tilde operator not implemented for {}.", expr.to_type()),
+                    )),
+                    (_, TokenType::Minus) => Err(LoxError::runtime(
+                        ErrorCode::InvalidOperands,
+                        operator,
+                        &format!("Minus operator not implemented for {}.", expr.to_type()),
                     )),
                     (value, TokenType::Bang) => Ok(LiteralValue::from_bool(!value.is_truthy())),
-                    (_, token_type) => Err(format!("{token_type} is not a valid unary operator.")),
+                    (_, token_type) => Err(LoxError::runtime(
+                        ErrorCode::InvalidOperands,
+                        operator,
+                        &format!("{token_type} is not a valid unary operator."),
+                    )),
                 }
             }
-            Expr::Variable { name } => match self.environment.get(&name.lexeme) {
+            Expr::Update {
+                target,
+                operator,
+                value,
+                postfix,
+                ..
+            } => {
+                let (old, new) = self.update(target, operator, value)?;
+                Ok(if *postfix { old } else { new })
+            }
+            Expr::Variable { id, name, .. } => match self.look_up_variable(name, *id) {
                 Some(value) => Ok(value),
-                None => Err(format!("Variable '{}' has not been declared.", name.lexeme)),
+                None => Err(LoxError::runtime(
+                    ErrorCode::UndefinedVariable,
+                    name,
+                    &format!("Variable '{}' has not been declared.", name.lexeme),
+                )),
             },
         }
     }
 
+    fn assign_variable(
+        &mut self,
+        id: usize,
+        name: &Token,
+        value: &LiteralValue,
+    ) -> Result<(), LoxError> {
+        match self.locals.get(&id) {
+            Some(distance) => {
+                self.environment
+                    .borrow_mut()
+                    .assign_at(*distance, name.clone(), value)
+            }
+            None => self.globals.borrow_mut().assign(name.clone(), value),
+        }
+    }
+
+    /// Applies a compound assignment or increment to `target`, evaluating
+    /// its object and index only once. Returns the old and new values.
+    fn update(
+        &mut self,
+        target: &Expr,
+        operator: &Token,
+        value: &Expr,
+    ) -> Result<(LiteralValue, LiteralValue), LoxError> {
+        let op = match operator.token_type {
+            TokenType::PlusEqual | TokenType::PlusPlus => TokenType::Plus,
+            TokenType::MinusEqual | TokenType::MinusMinus => TokenType::Minus,
+            TokenType::StarEqual => TokenType::Star,
+            TokenType::SlashEqual => TokenType::Slash,
+            TokenType::PercentEqual => TokenType::Percent,
+            other => unreachable!("{other} is not an update operator"),
+        };
+
+        match target {
+            Expr::Variable { id, name, .. } => {
+                let old = self.evaluate(target)?;
+                let new = Self::binary(&old, op, &self.evaluate(value)?, operator)?;
+                self.assign_variable(*id, name, &new)?;
+                Ok((old, new))
+            }
+            Expr::Get { object, name, .. } => match self.evaluate(object)? {
+                LiteralValue::Instance(instance) => {
+                    let old = LoxInstance::get(&instance, name)?;
+                    let new = Self::binary(&old, op, &self.evaluate(value)?, operator)?;
+                    instance.borrow_mut().set(name, new.clone());
+                    Ok((old, new))
+                }
+                _ => Err(LoxError::runtime(
+                    ErrorCode::NotAnInstance,
+                    name,
+                    "Only instances have fields.",
+                )),
+            },
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => {
+                let object = self.evaluate(object)?;
+                let index = self.evaluate(index)?;
+                let old = self.get_index(object.clone(), &index, bracket)?;
+                let new = Self::binary(&old, op, &self.evaluate(value)?, operator)?;
+                self.set_index(object, index, new.clone(), bracket)?;
+                Ok((old, new))
+            }
+            _ => unreachable!("the parser only builds updates of assignable targets"),
+        }
+    }
+
+    /// Applies the arithmetic or comparison `op` to two evaluated operands.
+    /// `operator` is the token errors point at, which for compound
+    /// assignments is the `+=` rather than a `+`.
+    fn binary(
+        left: &LiteralValue,
+        op: TokenType,
+        right: &LiteralValue,
+        operator: &Token,
+    ) -> Result<LiteralValue, LoxError> {
+        match (left, op, right) {
+            (x, TokenType::BangEqual, y) => Ok(LiteralValue::from_bool(x != y)),
+            (x, TokenType::EqualEqual, y) => Ok(LiteralValue::from_bool(x == y)),
+            (LiteralValue::Int(x), _, LiteralValue::Int(y)) => {
+                Self::int_binary(*x, op, *y, operator)
+            }
+            // Promoting an Int above 2^53 to a float rounds it, so mixed
+            // comparisons are made exactly instead.
+            (
+                LiteralValue::Int(x),
+                TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual,
+                LiteralValue::Number(y),
+            ) => Ok(Self::ordering(op, Self::compare_int_float(*x, *y))),
+            (
+                LiteralValue::Number(x),
+                TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::Greater
+                | TokenType::GreaterEqual,
+                LiteralValue::Int(y),
+            ) => Ok(Self::ordering(
+                op,
+                Self::compare_int_float(*y, *x).map(Ordering::reverse),
+            )),
+            // Mixed arithmetic promotes the integer to a float.
+            (LiteralValue::Int(x), _, LiteralValue::Number(_)) => {
+                Self::binary(&LiteralValue::Number(*x as f64), op, right, operator)
+            }
+            (LiteralValue::Number(_), _, LiteralValue::Int(y)) => {
+                Self::binary(left, op, &LiteralValue::Number(*y as f64), operator)
+            }
+            (LiteralValue::Number(x), TokenType::Plus, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x + y))
+            }
+            (LiteralValue::Number(x), TokenType::Minus, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x - y))
+            }
+            (LiteralValue::Number(x), TokenType::Star, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x * y))
+            }
+            (LiteralValue::Number(x), TokenType::Slash, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x / y))
+            }
+            (LiteralValue::Number(x), TokenType::Percent, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x % y))
+            }
+            (LiteralValue::Number(_), TokenType::Div, LiteralValue::Number(y)) if *y == 0.0 => {
+                Err(LoxError::runtime(
+                    ErrorCode::DivisionByZero,
+                    operator,
+                    "Integer division by zero.",
+                ))
+            }
+            // Truncating, so that `x == (x div y) * y + x % y`.
+            (LiteralValue::Number(x), TokenType::Div, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number((x / y).trunc()))
+            }
+            (LiteralValue::Number(x), TokenType::StarStar, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::Number(x.powf(*y)))
+            }
+            (
+                LiteralValue::Number(x),
+                TokenType::Ampersand
+                | TokenType::Pipe
+                | TokenType::Caret
+                | TokenType::LessLess
+                | TokenType::GreaterGreater,
+                LiteralValue::Number(y),
+            ) => match (float_to_int(*x), float_to_int(*y)) {
+                (Some(x), Some(y)) => Self::bitwise(x, op, y, operator),
+                _ => Err(LoxError::runtime(
+                    ErrorCode::InvalidOperands,
+                    operator,
+                    &format!("{op} needs integral operands, got {x} and {y}."),
+                )),
+            },
+            (LiteralValue::Number(x), TokenType::Greater, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::from_bool(x > y))
+            }
+            (LiteralValue::Number(x), TokenType::GreaterEqual, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::from_bool(x >= y))
+            }
+            (LiteralValue::Number(x), TokenType::Less, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::from_bool(x < y))
+            }
+            (LiteralValue::Number(x), TokenType::LessEqual, LiteralValue::Number(y)) => {
+                Ok(LiteralValue::from_bool(x <= y))
+            }
+            (LiteralValue::Int(_) | LiteralValue::Number(_), tt, LiteralValue::StringValue(_))
+            | (LiteralValue::StringValue(_), tt, LiteralValue::Int(_) | LiteralValue::Number(_)) => {
+                Err(LoxError::runtime(
+                    ErrorCode::InvalidOperands,
+                    operator,
+                    &format!("{tt} is not supported for String and Number"),
+                ))
+            }
+            (LiteralValue::StringValue(s1), TokenType::Plus, LiteralValue::StringValue(s2)) => {
+                Ok(LiteralValue::StringValue(format!("{s1}{s2}")))
+            }
+            (LiteralValue::StringValue(s1), TokenType::Greater, LiteralValue::StringValue(s2)) => {
+                Ok(LiteralValue::from_bool(s1 > s2))
+            }
+            (
+                LiteralValue::StringValue(s1),
+                TokenType::GreaterEqual,
+                LiteralValue::StringValue(s2),
+            ) => Ok(LiteralValue::from_bool(s1 >= s2)),
+            (LiteralValue::StringValue(s1), TokenType::Less, LiteralValue::StringValue(s2)) => {
+                Ok(LiteralValue::from_bool(s1 < s2))
+            }
+            (
+                LiteralValue::StringValue(s1),
+                TokenType::LessEqual,
+                LiteralValue::StringValue(s2),
+            ) => Ok(LiteralValue::from_bool(s1 <= s2)),
+            (x, tt, y) => Err(LoxError::runtime(
+                ErrorCode::InvalidOperands,
+                operator,
+                &format!("{tt} is not supported for {x:?} and {y:?}"),
+            )),
+        }
+    }
+
+    /// Compares an Int with a Float exactly. `None` when `y` is NaN.
+    fn compare_int_float(x: i64, y: f64) -> Option<Ordering> {
+        if y.is_nan() {
+            return None;
+        }
+        // `i64::MAX as f64` rounds up to 2^63, which no Int reaches.
+        if y >= i64::MAX as f64 {
+            return Some(Ordering::Less);
+        }
+        if y < i64::MIN as f64 {
+            return Some(Ordering::Greater);
+        }
+
+        let floor = y.floor();
+        match x.cmp(&(floor as i64)) {
+            Ordering::Equal if y > floor => Some(Ordering::Less),
+            ordering => Some(ordering),
+        }
+    }
+
+    fn ordering(op: TokenType, ordering: Option<Ordering>) -> LiteralValue {
+        let result = match op {
+            TokenType::Less => ordering == Some(Ordering::Less),
+            TokenType::LessEqual => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+            TokenType::Greater => ordering == Some(Ordering::Greater),
+            TokenType::GreaterEqual => {
+                matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+            }
+            _ => unreachable!("{op:?} is not a comparison."),
+        };
+        LiteralValue::from_bool(result)
+    }
+
+    /// Integer arithmetic is checked, so overflow is an error instead of
+    /// wrapping around. `/` and negative powers fall back to floats, since
+    /// their results are usually fractional.
+    fn int_binary(
+        x: i64,
+        op: TokenType,
+        y: i64,
+        operator: &Token,
+    ) -> Result<LiteralValue, LoxError> {
+        let result = match op {
+            TokenType::Plus => x.checked_add(y),
+            TokenType::Minus => x.checked_sub(y),
+            TokenType::Star => x.checked_mul(y),
+            TokenType::Percent | TokenType::Div if y == 0 => {
+                let message = match op {
+                    TokenType::Percent => "Integer modulo by zero.",
+                    _ => "Integer division by zero.",
+                };
+                return Err(LoxError::runtime(
+                    ErrorCode::DivisionByZero,
+                    operator,
+                    message,
+                ));
+            }
+            TokenType::Percent => x.checked_rem(y),
+            TokenType::Div => x.checked_div(y),
+            TokenType::StarStar if y >= 0 => u32::try_from(y).ok().and_then(|y| x.checked_pow(y)),
+            TokenType::Greater => return Ok(LiteralValue::from_bool(x > y)),
+            TokenType::GreaterEqual => return Ok(LiteralValue::from_bool(x >= y)),
+            TokenType::Less => return Ok(LiteralValue::from_bool(x < y)),
+            TokenType::LessEqual => return Ok(LiteralValue::from_bool(x <= y)),
+            TokenType::Ampersand
+            | TokenType::Pipe
+            | TokenType::Caret
+            | TokenType::LessLess
+            | TokenType::GreaterGreater => return Self::bitwise(x, op, y, operator),
+            _ => {
+                return Self::binary(
+                    &LiteralValue::Number(x as f64),
+                    op,
+                    &LiteralValue::Number(y as f64),
+                    operator,
+                )
+            }
+        };
+
+        result.map(LiteralValue::Int).ok_or_else(|| {
+            LoxError::runtime(
+                ErrorCode::IntegerOverflow,
+                operator,
+                &format!("{op} overflowed for {x} and {y}."),
+            )
+        })
+    }
+
+    fn bitwise(x: i64, op: TokenType, y: i64, operator: &Token) -> Result<LiteralValue, LoxError> {
+        let result = match op {
+            TokenType::Ampersand => x & y,
+            TokenType::Pipe => x | y,
+            TokenType::Caret => x ^ y,
+            TokenType::LessLess | TokenType::GreaterGreater => {
+                let shift = match u32::try_from(y) {
+                    Ok(shift) if shift < 64 => shift,
+                    _ => {
+                        return Err(LoxError::runtime(
+                            ErrorCode::InvalidOperands,
+                            operator,
+                            &format!("Can't shift by {y}, the amount must be from 0 to 63."),
+                        ))
+                    }
+                };
+                if op == TokenType::LessLess {
+                    x << shift
+                } else {
+                    x >> shift
+                }
+            }
+            other => unreachable!("{other} is not a bitwise operator"),
+        };
+        Ok(LiteralValue::Int(result))
+    }
+
     fn lambda_name(&mut self) -> String {
         let res = format!("__lambda_{}", self.lambda_counter);
         self.lambda_counter += 1;
         res
     }
 
-    fn call(&mut self, callee_expr: &Expr, arg_exprs: &[Expr]) -> Result<LiteralValue, String> {
-        let callee = self.evaluate(callee_expr)?;
-
-        if let Some(callable) = callee.as_callable() {
-            let maybe_args: Result<Vec<_>, _> =
-                arg_exprs.iter().map(|arg| self.evaluate(arg)).collect();
-            match maybe_args {
-                Ok(args) => {
-                    if args.len() != callable.arity() {
-                        Err(format!(
-                            "Call for '{}' expected {} args, got {}",
-                            callable.name(),
-                            callable.arity(),
-                            args.len()
-                        ))
-                    } else {
-                        callable.call(self, &args)
+    /// Reads `object[index]` from a list or map.
+    fn get_index(
+        &self,
+        object: LiteralValue,
+        index: &LiteralValue,
+        bracket: &Token,
+    ) -> Result<LiteralValue, LoxError> {
+        match object {
+            LiteralValue::List(list) => {
+                let list = list.borrow();
+                let position = natives::list_index(index, list.len(), false)
+                    .map_err(|(code, msg)| LoxError::runtime(code, bracket, &msg))?;
+                Ok(list[position].clone())
+            }
+            LiteralValue::Map(map) => map
+                .borrow()
+                .get(index)
+                .map_err(|msg| LoxError::runtime(ErrorCode::UnhashableKey, bracket, &msg))?
+                .ok_or_else(|| {
+                    LoxError::runtime(
+                        ErrorCode::KeyNotFound,
+                        bracket,
+                        &format!("Key {} not found in map.", index.element_string()),
+                    )
+                    .with_help("check for the key with 'has' first")
+                }),
+            other => Err(Self::not_indexable(&other, bracket)),
+        }
+    }
+
+    /// Writes `object[index] = value` into a list or map.
+    fn set_index(
+        &self,
+        object: LiteralValue,
+        index: LiteralValue,
+        value: LiteralValue,
+        bracket: &Token,
+    ) -> Result<(), LoxError> {
+        match object {
+            LiteralValue::List(list) => {
+                let len = list.borrow().len();
+                let position = natives::list_index(&index, len, false)
+                    .map_err(|(code, msg)| LoxError::runtime(code, bracket, &msg))?;
+                list.borrow_mut()[position] = value;
+                Ok(())
+            }
+            LiteralValue::Map(map) => map
+                .borrow_mut()
+                .insert(index, value)
+                .map_err(|msg| LoxError::runtime(ErrorCode::UnhashableKey, bracket, &msg)),
+            other => Err(Self::not_indexable(&other, bracket)),
+        }
+    }
+
+    fn not_indexable(object: &LiteralValue, bracket: &Token) -> LoxError {
+        LoxError::runtime(
+            ErrorCode::NotIndexable,
+            bracket,
+            &format!("Can't index into a value of type {}.", object.to_type()),
+        )
+    }
+
+    /// Evaluates a chain of calls, property accesses and indexes. Returns
+    /// `None` when a `?.` link found nil, which skips the rest of the chain,
+    /// so `user?.name.first()` is nil rather than an error when `user` is nil.
+    fn evaluate_chain(&mut self, expr: &Expr) -> Result<Option<LiteralValue>, LoxError> {
+        match expr {
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+                optional,
+                ..
+            } => {
+                let callee = match self.evaluate_chain(callee)? {
+                    Some(LiteralValue::Nil) if *optional => return Ok(None),
+                    Some(callee) => callee,
+                    None => return Ok(None),
+                };
+                let maybe_args: Result<Vec<_>, _> =
+                    arguments.iter().map(|arg| self.evaluate(arg)).collect();
+                self.call_value(callee, paren, maybe_args?).map(Some)
+            }
+            Expr::Get {
+                object,
+                name,
+                optional,
+                ..
+            } => match self.evaluate_chain(object)? {
+                Some(LiteralValue::Instance(instance)) => {
+                    LoxInstance::get(&instance, name).map(Some)
+                }
+                Some(LiteralValue::Nil) if *optional => Ok(None),
+                Some(_) => Err(LoxError::runtime(
+                    ErrorCode::NotAnInstance,
+                    name,
+                    "Only instances have properties.",
+                )),
+                None => Ok(None),
+            },
+            Expr::Index {
+                object,
+                bracket,
+                index,
+                ..
+            } => match self.evaluate_chain(object)? {
+                Some(object) => {
+                    let index = self.evaluate(index)?;
+                    self.get_index(object, &index, bracket).map(Some)
+                }
+                None => Ok(None),
+            },
+            _ => self.evaluate(expr).map(Some),
+        }
+    }
+
+    /// Calls `callee` with already evaluated `args`, recording a call frame
+    /// for stack traces.
+    fn call_value(
+        &mut self,
+        callee: LiteralValue,
+        paren: &Token,
+        args: Vec<LiteralValue>,
+    ) -> Result<LiteralValue, LoxError> {
+        let arity = match &callee {
+            LiteralValue::Callable(callable) => callable.arity(),
+            LiteralValue::Class(class) => class.arity(),
+            _ => {
+                return Err(LoxError::runtime(
+                    ErrorCode::NotCallable,
+                    paren,
+                    &format!("Attempted to call non-callable '{callee}'"),
+                ))
+            }
+        };
+
+        if args.len() != arity {
+            return Err(LoxError::runtime(
+                ErrorCode::ArityMismatch,
+                paren,
+                &format!(
+                    "Call for '{}' expected {} args, got {}",
+                    callee,
+                    arity,
+                    args.len()
+                ),
+            ));
+        }
+
+        let name = match &callee {
+            LiteralValue::Callable(callable) => callable.name(),
+            LiteralValue::Class(class) => class.name.clone(),
+            _ => unreachable!(),
+        };
+        if self.frames.len() >= MAX_CALL_DEPTH {
+            return Err(LoxError::runtime(
+                ErrorCode::StackOverflow,
+                paren,
+                &format!("Calling '{name}' exceeded the maximum call depth of {MAX_CALL_DEPTH}."),
+            )
+            .with_help("check that every recursive call reaches a base case")
+            .with_trace(self.stack_trace()));
+        }
+        self.frames.push(CallFrame {
+            name,
+            line: paren.span.line,
+        });
+
+        let result = match callee {
+            LiteralValue::Callable(callable) => callable.call(self, paren, &args),
+            LiteralValue::Class(class) => LoxClass::instantiate(&class, self, paren, &args),
+            _ => unreachable!(),
+        }
+        .map_err(|err| err.with_trace(self.stack_trace()));
+
+        self.frames.pop();
+        result
+    }
+
+    /// Starts iterating over `value` for a `for-in` loop. `keyword` is the
+    /// loop's `in` token, used to locate errors.
+    fn iterator(&mut self, value: LiteralValue, keyword: &Token) -> Result<LoxIterator, LoxError> {
+        let items: Vec<LiteralValue> = match value {
+            LiteralValue::List(list) => return Ok(LoxIterator::List(list, 0)),
+            LiteralValue::Range(range) => return Ok(LoxIterator::Range { range, next: 0 }),
+            LiteralValue::Map(map) => map
+                .borrow()
+                .entries()
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect(),
+            LiteralValue::StringValue(s) => s
+                .chars()
+                .map(|c| LiteralValue::StringValue(c.to_string()))
+                .collect(),
+            LiteralValue::Instance(instance) => {
+                let iter = instance.borrow().class.find_method("iter");
+                let iterator = match iter {
+                    Some(iter) => {
+                        let iter = iter.bind(LiteralValue::Instance(instance.clone()));
+                        match self.call_value(LiteralValue::Callable(iter), keyword, vec![])? {
+                            // Anything else `iter()` returns, like a list or
+                            // a range, is iterated in its own way.
+                            LiteralValue::Instance(iterator) => LiteralValue::Instance(iterator),
+                            other => return self.iterator(other, keyword),
+                        }
                     }
+                    None => LiteralValue::Instance(instance),
+                };
+
+                let next = match &iterator {
+                    LiteralValue::Instance(instance) => instance.borrow().class.find_method("next"),
+                    _ => None,
+                };
+                return match next {
+                    Some(next) => Ok(LoxIterator::Instance(next.bind(iterator))),
+                    None => Err(LoxError::runtime(
+                        ErrorCode::NotIterable,
+                        keyword,
+                        &format!("Can't iterate over {iterator}, it has no 'next' method."),
+                    )
+                    .with_help(
+                        "define 'next()' returning nil when done, or 'iter()' returning such an instance",
+                    )),
+                };
+            }
+            other => {
+                return Err(LoxError::runtime(
+                    ErrorCode::NotIterable,
+                    keyword,
+                    &format!("Can't iterate over a value of type {}.", other.to_type()),
+                ))
+            }
+        };
+        Ok(LoxIterator::Items(items.into_iter()))
+    }
+
+    /// Advances `iterator`, returning `None` once it is exhausted.
+    fn next_item(
+        &mut self,
+        iterator: &mut LoxIterator,
+        keyword: &Token,
+    ) -> Result<Option<LiteralValue>, LoxError> {
+        match iterator {
+            LoxIterator::List(list, position) => {
+                let item = list.borrow().get(*position).cloned();
+                *position += 1;
+                Ok(item)
+            }
+            LoxIterator::Items(items) => Ok(items.next()),
+            LoxIterator::Range { range, next } => {
+                let item = range.get(*next);
+                *next += 1;
+                Ok(item)
+            }
+            LoxIterator::Instance(next) => {
+                match self.call_value(LiteralValue::Callable(next.clone()), keyword, vec![])? {
+                    LiteralValue::Nil => Ok(None),
+                    item => Ok(Some(item)),
                 }
-                Err(msg) => Err(msg),
             }
-        } else {
-            Err(format!("Attempted to call non-callable '{callee}'"))
         }
     }
 
-    pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<(), String> {
+    /// The value a `catch` clause binds for `err`: the thrown value itself, or
+    /// an `Error` instance describing a built-in runtime error.
+    fn error_value(&self, err: &LoxError) -> LiteralValue {
+        if let Some(value) = err.thrown() {
+            return value.clone();
+        }
+
+        let trace = err
+            .trace()
+            .iter()
+            .map(|frame| LiteralValue::StringValue(frame.clone()))
+            .collect();
+        let mut instance = LoxInstance::new(self.error_class.clone());
+        for (field, value) in [
+            (
+                "message",
+                LiteralValue::StringValue(err.message().to_string()),
+            ),
+            ("code", LiteralValue::StringValue(err.code().to_string())),
+            ("line", LiteralValue::Int(err.line() as i64)),
+            ("trace", LiteralValue::List(Rc::new(RefCell::new(trace)))),
+        ] {
+            instance.set(&Token::global(field), value);
+        }
+        LiteralValue::Instance(Rc::new(RefCell::new(instance)))
+    }
+
+    /// Formats the active call frames, innermost first.
+    fn stack_trace(&self) -> Vec<String> {
+        self.frames
+            .iter()
+            .rev()
+            .map(|frame| frame.to_string())
+            .collect()
+    }
+
+    pub fn interpret(&mut self, stmts: &[Stmt]) -> Result<(), LoxError> {
         for stmt in stmts {
-            self.execute(stmt)?
+            self.execute(stmt)?;
         }
         Ok(())
     }
 
-    fn execute(&mut self, stmt: &Stmt) -> Result<(), String> {
-        if self.return_value.is_some() {
-            return Ok(());
+    /// Runs `stmts` in order, stopping at the first one that does not
+    /// complete normally.
+    fn execute_stmts(&mut self, stmts: &[Stmt]) -> Result<ControlFlow, LoxError> {
+        for stmt in stmts {
+            match self.execute(stmt)? {
+                ControlFlow::Normal => (),
+                flow => return Ok(flow),
+            }
         }
+        Ok(ControlFlow::Normal)
+    }
+
+    /// Runs `stmts` with `environment` as the current scope, restoring the
+    /// previous scope afterwards even if a statement fails.
+    pub fn execute_block(
+        &mut self,
+        stmts: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<ControlFlow, LoxError> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.execute_stmts(stmts);
+        self.environment = previous;
+        result
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<ControlFlow, LoxError> {
         match stmt {
-            Stmt::Block { statements } => {
-                self.environment = Environment::with_enclosing(self.environment.clone());
-                for statement in statements.iter() {
-                    self.execute(statement)?;
-                }
+            Stmt::Block { statements, .. } => {
+                let env = Environment::with_enclosing(self.environment.clone());
+                return self.execute_block(statements, Rc::new(RefCell::new(env)));
+            }
+            Stmt::Break { .. } => return Ok(ControlFlow::Break),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => {
+                let superclass = match superclass {
+                    Some(superclass_expr) => match self.evaluate(superclass_expr)? {
+                        LiteralValue::Class(class) => Some(class),
+                        _ => {
+                            return Err(LoxError::runtime(
+                                ErrorCode::InvalidSuperclass,
+                                name,
+                                &format!("Superclass of '{}' must be a class.", name.lexeme),
+                            ))
+                        }
+                    },
+                    None => None,
+                };
 
-                if let Some(enclosing) = self.environment.enclosing.clone() {
-                    self.environment = *enclosing
-                } else {
-                    panic!("impossible to reach here");
+                let closure = match &superclass {
+                    Some(class) => {
+                        let mut env = Environment::with_enclosing(self.environment.clone());
+                        env.define(Token::global("super"), LiteralValue::Class(class.clone()));
+                        Rc::new(RefCell::new(env))
+                    }
+                    None => self.environment.clone(),
+                };
+
+                let mut class_methods = HashMap::new();
+                for method in methods {
+                    if let Stmt::Function {
+                        name: method_name,
+                        params,
+                        body,
+                        ..
+                    } = method
+                    {
+                        let function = LoxCallable::LoxFunction {
+                            name: method_name.lexeme.clone(),
+                            parameters: params.clone(),
+                            body: body.clone(),
+                            closure: closure.clone(),
+                            is_initializer: method_name.lexeme == "init",
+                        };
+                        class_methods.insert(method_name.lexeme.clone(), function);
+                    }
                 }
+
+                let class = LoxClass::new(name.lexeme.clone(), superclass, class_methods);
+                self.environment
+                    .borrow_mut()
+                    .define(name.clone(), LiteralValue::Class(Rc::new(class)));
             }
-            Stmt::Expression { expression } => {
+            Stmt::Continue { .. } => return Ok(ControlFlow::Continue),
+            Stmt::Expression { expression, .. } => {
                 self.evaluate(expression)?;
             }
-            Stmt::Function { name, params, body } => {
+            Stmt::Function {
+                name, params, body, ..
+            } => {
                 let callable = LiteralValue::Callable(LoxCallable::LoxFunction {
                     name: name.lexeme.clone(),
                     parameters: params.clone(),
                     body: body.clone(),
                     closure: self.environment.clone(),
+                    is_initializer: false,
                 });
 
-                self.environment.define(name.clone(), callable);
+                self.environment.borrow_mut().define(name.clone(), callable);
             }
             Stmt::If {
                 condition,
                 then_stmt,
                 else_stmt,
+                ..
             } => {
                 let truth_value = self.evaluate(condition)?;
                 if truth_value.is_truthy() {
-                    self.execute(then_stmt)?
+                    return self.execute(then_stmt);
                 } else if let Some(els) = else_stmt {
-                    self.execute(els)?
+                    return self.execute(els);
                 }
             }
-            Stmt::Print { expression } => {
+            Stmt::Print { expression, .. } => {
                 let result = self.evaluate(expression)?;
                 println!("{result}");
             }
-            Stmt::Return { keyword: _, value } => {
+            Stmt::Return {
+                keyword: _, value, ..
+            } => {
                 let value = if let Some(expr) = value {
                     self.evaluate(expr)?
                 } else {
                     LiteralValue::Nil
                 };
-                self.return_value = Some(value);
+                return Ok(ControlFlow::Return(value));
             }
-            Stmt::Var { name, initializer } => {
+            Stmt::Var {
+                name, initializer, ..
+            } => {
                 let value = self.evaluate(initializer)?;
-                self.environment.define(name.clone(), value);
+                self.environment.borrow_mut().define(name.clone(), value);
+            }
+            Stmt::ForIn {
+                name,
+                keyword,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable = self.evaluate(iterable)?;
+                let mut iterator = self.iterator(iterable, keyword)?;
+                while let Some(item) = self.next_item(&mut iterator, keyword)? {
+                    // A fresh scope per item, so closures capture each one.
+                    let mut env = Environment::with_enclosing(self.environment.clone());
+                    env.define(name.clone(), item);
+                    let env = Rc::new(RefCell::new(env));
+                    match self.execute_block(std::slice::from_ref(body), env)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Normal | ControlFlow::Continue => (),
+                        flow @ ControlFlow::Return(_) => return Ok(flow),
+                    }
+                }
+            }
+            Stmt::Throw { keyword, value, .. } => {
+                let value = self.evaluate(value)?;
+                let message = match &value {
+                    LiteralValue::Instance(instance) => {
+                        let instance = instance.borrow();
+                        match instance.field("message") {
+                            Some(message) => format!("Uncaught {}: {message}", instance.class),
+                            None => format!("Uncaught {instance}."),
+                        }
+                    }
+                    other => format!("Uncaught exception: {other}"),
+                };
+                return Err(
+                    LoxError::runtime(ErrorCode::UncaughtException, keyword, &message)
+                        .with_thrown(value),
+                );
             }
-            Stmt::While { condition, body } => {
-                let mut flag = self.evaluate(condition)?;
-                while flag.is_truthy() {
-                    self.execute(body)?;
-                    flag = self.evaluate(condition)?;
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                let mut result = self.execute(body);
+                if let Some((name, handler)) = catch {
+                    // Only runtime errors are catchable; the others stop the
+                    // program before it runs.
+                    if let Err(err @ LoxError::Runtime(_)) = &result {
+                        // An error caught in the function that raised it has
+                        // not left a call yet, so it has no trace so far.
+                        let err = err.clone().with_trace(self.stack_trace());
+                        let mut env = Environment::with_enclosing(self.environment.clone());
+                        env.define(name.clone(), self.error_value(&err));
+                        let env = Rc::new(RefCell::new(env));
+                        result = self.execute_block(std::slice::from_ref(handler), env);
+                    }
+                }
+
+                // A `finally` that breaks, continues, returns or fails wins
+                // over whatever the try and catch blocks did.
+                if let Some(finally) = finally {
+                    match self.execute(finally)? {
+                        ControlFlow::Normal => (),
+                        flow => return Ok(flow),
+                    }
+                }
+                return result;
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                while self.evaluate(condition)?.is_truthy() {
+                    match self.execute(body)? {
+                        ControlFlow::Break => break,
+                        ControlFlow::Normal | ControlFlow::Continue => (),
+                        flow @ ControlFlow::Return(_) => return Ok(flow),
+                    }
+                    if let Some(increment) = increment {
+                        self.evaluate(increment)?;
+                    }
                 }
             }
         };
-        Ok(())
+        Ok(ControlFlow::Normal)
     }
 }
@@ -0,0 +1,14 @@
+pub mod callable;
+pub mod class;
+pub mod diagnostic;
+pub mod environment;
+pub mod error;
+pub mod expression;
+pub mod interpreter;
+pub mod map;
+pub mod natives;
+pub mod parser;
+pub mod range;
+pub mod resolver;
+pub mod scanner;
+pub mod statement;
@@ -1,37 +1,48 @@
-mod callable;
-mod environment;
-mod expression;
-mod interpreter;
-mod parser;
-mod scanner;
-mod statement;
+#[cfg(test)]
 mod tests;
-use interpreter::Interpreter;
-use parser::Parser;
 
-use crate::scanner::*;
+use lox_lang::diagnostic::Renderer;
+use lox_lang::error::LoxError;
+use lox_lang::interpreter::Interpreter;
+use lox_lang::parser::Parser;
+use lox_lang::resolver::Resolver;
+use lox_lang::scanner::Scanner;
 use std::env;
 use std::fs;
 use std::io;
-use std::io::Write;
+use std::io::{IsTerminal, Write};
 use std::process::exit;
+use std::thread;
 
 fn run_file(path: &str) -> Result<(), String> {
     let mut interpreter = Interpreter::new();
     match fs::read_to_string(path) {
-        Err(msg) => Err(msg.to_string()),
-        Ok(contents) => run(&mut interpreter, &contents),
+        Err(msg) => Err(format!("error: {msg}")),
+        Ok(contents) => run(&mut interpreter, &contents).map_err(|errors| {
+            let errors: Vec<LoxError> = errors.into_iter().map(|err| err.with_file(path)).collect();
+            report(&errors, &contents)
+        }),
     }
 }
 
-fn run(interpreter: &mut Interpreter, contents: &str) -> Result<(), String> {
+fn report(errors: &[LoxError], source: &str) -> String {
+    Renderer::new(source, io::stdout().is_terminal()).render_all(errors)
+}
+
+fn run(interpreter: &mut Interpreter, contents: &str) -> Result<(), Vec<LoxError>> {
     let mut scanner = Scanner::new(contents);
     let tokens = scanner.scan_tokens()?;
 
     let mut parser = Parser::new(tokens);
     let statements = parser.parse()?;
 
-    interpreter.interpret(&statements)?;
+    let resolver = Resolver::new();
+    let locals = resolver.resolve(&statements)?;
+    interpreter.resolve(locals);
+
+    interpreter
+        .interpret(&statements)
+        .map_err(|err| vec![err])?;
 
     Ok(())
 }
@@ -57,17 +68,33 @@ fn run_prompt() -> Result<(), String> {
         if value == ".exit" {
             break;
         }
-        run(&mut interpreter, value)?;
+        if let Err(errors) = run(&mut interpreter, value) {
+            println!("{}", report(&errors, value));
+        }
     }
     Ok(())
 }
 
 pub fn run_string(contents: &str) -> Result<(), String> {
     let mut interpreter = Interpreter::new();
-    run(&mut interpreter, contents)
+    run(&mut interpreter, contents).map_err(|errors| report(&errors, contents))
 }
 
+/// Stack for the thread that runs Lox code. Every Lox call nests several
+/// interpreter calls, so this needs to hold `MAX_CALL_DEPTH` of them even in
+/// debug builds. It is only reserved, not used, until deep recursion needs it.
+const INTERPRETER_STACK_SIZE: usize = 256 * 1024 * 1024;
+
 fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(INTERPRETER_STACK_SIZE)
+        .spawn(run_args)
+        .expect("Could not start the interpreter thread.");
+    let code = interpreter.join().unwrap_or(101);
+    exit(code)
+}
+
+fn run_args() -> i32 {
     let args: Vec<String> = env::args().collect();
 
     let result = match args.len() {
@@ -81,10 +108,10 @@ fn main() {
     };
 
     match result {
-        Ok(_) => exit(0),
+        Ok(_) => 0,
         Err(msg) => {
-            println!("Error:\n{msg}");
-            exit(1)
+            println!("{msg}");
+            1
         }
     }
 }
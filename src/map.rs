@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+
+use crate::expression::{float_to_int, LiteralValue};
+
+/// The hashable form of a map key. Only values compared by content can be
+/// keys; callables, classes, instances and collections are rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum MapKey {
+    Int(i64),
+    Number(u64),
+    String(String),
+    Bool(bool),
+}
+
+impl MapKey {
+    fn from_value(value: &LiteralValue) -> Result<Self, String> {
+        match value {
+            LiteralValue::Int(i) => Ok(MapKey::Int(*i)),
+            LiteralValue::Number(x) if x.is_nan() => {
+                Err(String::from("Can't use NaN as a map key."))
+            }
+            // Whole floats equal the matching integer, and `0.0 == -0.0`, so
+            // they must hash the same.
+            LiteralValue::Number(x) => match float_to_int(*x) {
+                Some(i) => Ok(MapKey::Int(i)),
+                None => Ok(MapKey::Number(x.to_bits())),
+            },
+            LiteralValue::StringValue(s) => Ok(MapKey::String(s.clone())),
+            LiteralValue::True => Ok(MapKey::Bool(true)),
+            LiteralValue::False => Ok(MapKey::Bool(false)),
+            other => Err(format!(
+                "Can't use a value of type {} as a map key.",
+                other.to_type()
+            )),
+        }
+    }
+}
+
+/// A map that iterates in insertion order.
+#[derive(Clone, Default)]
+pub struct LoxMap {
+    entries: Vec<(LiteralValue, LiteralValue)>,
+    positions: HashMap<MapKey, usize>,
+}
+
+impl LoxMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, key: &LiteralValue) -> Result<Option<LiteralValue>, String> {
+        let key = MapKey::from_value(key)?;
+        Ok(self
+            .positions
+            .get(&key)
+            .map(|&position| self.entries[position].1.clone()))
+    }
+
+    /// Sets `key` to `value`. A new key goes last; an existing key keeps its place.
+    pub fn insert(&mut self, key: LiteralValue, value: LiteralValue) -> Result<(), String> {
+        match self.positions.get(&MapKey::from_value(&key)?) {
+            Some(&position) => self.entries[position].1 = value,
+            None => {
+                self.positions
+                    .insert(MapKey::from_value(&key)?, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes `key`, returning its value if it was present.
+    pub fn remove(&mut self, key: &LiteralValue) -> Result<Option<LiteralValue>, String> {
+        let position = match self.positions.remove(&MapKey::from_value(key)?) {
+            Some(position) => position,
+            None => return Ok(None),
+        };
+
+        let (_, value) = self.entries.remove(position);
+        for later in self.positions.values_mut() {
+            if *later > position {
+                *later -= 1;
+            }
+        }
+        Ok(Some(value))
+    }
+
+    pub fn contains_key(&self, key: &LiteralValue) -> Result<bool, String> {
+        Ok(self.positions.contains_key(&MapKey::from_value(key)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The key-value pairs in insertion order.
+    pub fn entries(&self) -> &[(LiteralValue, LiteralValue)] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(s: &str) -> LiteralValue {
+        LiteralValue::StringValue(s.to_string())
+    }
+
+    #[test]
+    fn keep_insertion_order() {
+        let mut map = LoxMap::new();
+        for key in ["b", "a", "c"] {
+            map.insert(string(key), LiteralValue::Nil).unwrap();
+        }
+        map.insert(string("a"), LiteralValue::True).unwrap();
+        map.remove(&string("b")).unwrap();
+        map.insert(string("b"), LiteralValue::False).unwrap();
+
+        let keys: Vec<String> = map.entries().iter().map(|(k, _)| k.to_string()).collect();
+        assert_eq!(keys, vec!["a", "c", "b"]);
+        assert_eq!(map.get(&string("a")).unwrap(), Some(LiteralValue::True));
+        assert_eq!(map.get(&string("b")).unwrap(), Some(LiteralValue::False));
+    }
+
+    #[test]
+    fn hash_keys_by_content() {
+        let mut map = LoxMap::new();
+        map.insert(LiteralValue::Number(0.0), string("zero"))
+            .unwrap();
+        map.insert(LiteralValue::True, string("yes")).unwrap();
+
+        assert!(map.contains_key(&LiteralValue::Number(-0.0)).unwrap());
+        assert!(map.contains_key(&LiteralValue::Int(0)).unwrap());
+        assert!(!map.contains_key(&string("true")).unwrap());
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn reject_unhashable_keys() {
+        let mut map = LoxMap::new();
+
+        assert!(map.insert(LiteralValue::Nil, LiteralValue::Nil).is_err());
+        assert!(map.get(&LiteralValue::Number(f64::NAN)).is_err());
+        assert!(map.is_empty());
+    }
+}
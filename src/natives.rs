@@ -0,0 +1,337 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::callable::{CallableFunction, LoxCallable, NativeError};
+use crate::environment::Environment;
+use crate::error::ErrorCode;
+use crate::expression::{float_to_int, LiteralValue};
+use crate::interpreter::Interpreter;
+use crate::map::LoxMap;
+use crate::range::LoxRange;
+use crate::scanner::Token;
+
+/// Defines the built-in functions in the global scope.
+pub fn define_globals(globals: &mut Environment) {
+    let natives: [(&str, usize, CallableFunction); 15] = [
+        ("clock", 0, clock),
+        ("len", 1, len),
+        ("push", 2, push),
+        ("pop", 1, pop),
+        ("insert", 3, insert),
+        ("remove", 2, remove),
+        ("slice", 3, slice),
+        ("contains", 2, contains),
+        ("keys", 1, keys),
+        ("values", 1, values),
+        ("has", 2, has),
+        ("delete", 2, delete),
+        ("range", 3, range),
+        ("int", 1, int),
+        ("float", 1, float),
+    ];
+
+    for (name, arity, fun) in natives {
+        globals.define(
+            Token::global(name),
+            LiteralValue::Callable(LoxCallable::NativeFunction {
+                name: name.to_string(),
+                arity,
+                fun,
+            }),
+        );
+    }
+}
+
+/// Resolves `index` into a position in a list of `len` elements. Negative
+/// indexes count from the end. With `allow_end`, `len` itself is valid too,
+/// which is where `insert` appends.
+pub fn list_index(index: &LiteralValue, len: usize, allow_end: bool) -> Result<usize, NativeError> {
+    let index = match index {
+        LiteralValue::Int(i) => *i,
+        LiteralValue::Number(x) => match float_to_int(*x) {
+            Some(i) => i,
+            None => {
+                return Err((
+                    ErrorCode::InvalidIndex,
+                    format!("List index must be an integer, got {x}."),
+                ))
+            }
+        },
+        other => {
+            return Err((
+                ErrorCode::InvalidIndex,
+                format!("List index must be an Int, got {}.", other.to_type()),
+            ))
+        }
+    };
+
+    let limit = if allow_end { len + 1 } else { len };
+    let position = if index < 0 {
+        (len as i64).saturating_add(index)
+    } else {
+        index
+    };
+    if position < 0 || position >= limit as i64 {
+        return Err((
+            ErrorCode::IndexOutOfBounds,
+            format!("List index {index} is out of bounds for length {len}."),
+        ));
+    }
+
+    Ok(position as usize)
+}
+
+type List = Rc<RefCell<Vec<LiteralValue>>>;
+
+/// An error with no more specific code than `NativeFunction`.
+fn native_error(message: String) -> NativeError {
+    (ErrorCode::NativeFunction, message)
+}
+
+fn unhashable(message: String) -> NativeError {
+    (ErrorCode::UnhashableKey, message)
+}
+
+fn as_list(value: &LiteralValue, function: &str) -> Result<List, NativeError> {
+    match value {
+        LiteralValue::List(list) => Ok(list.clone()),
+        other => Err(native_error(format!(
+            "'{function}' expects a List, got {}.",
+            other.to_type()
+        ))),
+    }
+}
+
+fn as_map(value: &LiteralValue, function: &str) -> Result<Rc<RefCell<LoxMap>>, NativeError> {
+    match value {
+        LiteralValue::Map(map) => Ok(map.clone()),
+        other => Err(native_error(format!(
+            "'{function}' expects a Map, got {}.",
+            other.to_type()
+        ))),
+    }
+}
+
+fn clock(_: &Interpreter, _: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::SystemTime::UNIX_EPOCH)
+        .expect("Could not get system time.")
+        .as_millis();
+
+    Ok(LiteralValue::Number(now as f64 / 1000.0))
+}
+
+fn len(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    match &args[0] {
+        LiteralValue::List(list) => Ok(LiteralValue::Int(list.borrow().len() as i64)),
+        LiteralValue::Map(map) => Ok(LiteralValue::Int(map.borrow().len() as i64)),
+        LiteralValue::StringValue(s) => Ok(LiteralValue::Int(s.chars().count() as i64)),
+        LiteralValue::Range(range) => {
+            i64::try_from(range.len())
+                .map(LiteralValue::Int)
+                .map_err(|_| {
+                    (
+                        ErrorCode::IntegerOverflow,
+                        format!("The length of {range} doesn't fit in an Int."),
+                    )
+                })
+        }
+        other => Err(native_error(format!(
+            "'len' expects a List, Map, Range or String, got {}.",
+            other.to_type()
+        ))),
+    }
+}
+
+fn push(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    as_list(&args[0], "push")?
+        .borrow_mut()
+        .push(args[1].clone());
+    Ok(LiteralValue::Nil)
+}
+
+fn pop(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    as_list(&args[0], "pop")?
+        .borrow_mut()
+        .pop()
+        .ok_or_else(|| native_error(String::from("Can't pop from an empty List.")))
+}
+
+fn insert(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let list = as_list(&args[0], "insert")?;
+    let len = list.borrow().len();
+    let position = list_index(&args[1], len, true)?;
+    list.borrow_mut().insert(position, args[2].clone());
+    Ok(LiteralValue::Nil)
+}
+
+fn remove(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let list = as_list(&args[0], "remove")?;
+    let len = list.borrow().len();
+    let position = list_index(&args[1], len, false)?;
+    let removed = list.borrow_mut().remove(position);
+    Ok(removed)
+}
+
+/// Copies the elements from `start` up to but not including `end` into a
+/// new list. Both bounds may be negative and are clamped to the list.
+fn slice(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let list = as_list(&args[0], "slice")?;
+    let list = list.borrow();
+    let len = list.len() as i64;
+
+    let bound = |value: &LiteralValue| {
+        let x = match value {
+            LiteralValue::Int(i) => *i,
+            LiteralValue::Number(x) => float_to_int(*x).ok_or_else(|| {
+                native_error(format!("'slice' bounds must be integers, got {value}."))
+            })?,
+            other => {
+                return Err(native_error(format!(
+                    "'slice' bounds must be integers, got {other}."
+                )))
+            }
+        };
+        let x = if x < 0 { len.saturating_add(x) } else { x };
+        Ok(x.clamp(0, len) as usize)
+    };
+    let start = bound(&args[1])?;
+    let end = bound(&args[2])?.max(start);
+
+    Ok(LiteralValue::List(Rc::new(RefCell::new(
+        list[start..end].to_vec(),
+    ))))
+}
+
+fn contains(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let list = as_list(&args[0], "contains")?;
+    let found = list.borrow().contains(&args[1]);
+    Ok(LiteralValue::from_bool(found))
+}
+
+fn keys(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let map = as_map(&args[0], "keys")?;
+    let keys = map
+        .borrow()
+        .entries()
+        .iter()
+        .map(|(key, _)| key.clone())
+        .collect();
+    Ok(LiteralValue::List(Rc::new(RefCell::new(keys))))
+}
+
+fn values(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let map = as_map(&args[0], "values")?;
+    let values = map
+        .borrow()
+        .entries()
+        .iter()
+        .map(|(_, value)| value.clone())
+        .collect();
+    Ok(LiteralValue::List(Rc::new(RefCell::new(values))))
+}
+
+fn has(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let found = as_map(&args[0], "has")?
+        .borrow()
+        .contains_key(&args[1])
+        .map_err(unhashable)?;
+    Ok(LiteralValue::from_bool(found))
+}
+
+/// Removes a key from a map, returning its value, or nil if it was absent.
+fn delete(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    let removed = as_map(&args[0], "delete")?
+        .borrow_mut()
+        .remove(&args[1])
+        .map_err(unhashable)?;
+    Ok(removed.unwrap_or(LiteralValue::Nil))
+}
+
+/// The lazy range `start, start + step, ...`, stopping before `end`. The
+/// items are Ints when all three arguments are.
+fn range(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    if let [LiteralValue::Int(start), LiteralValue::Int(end), LiteralValue::Int(step)] = args {
+        return LoxRange::int(*start, *end, *step)
+            .map(LiteralValue::Range)
+            .map_err(native_error);
+    }
+
+    let number = |value: &LiteralValue| match value {
+        LiteralValue::Int(i) => Ok(*i as f64),
+        LiteralValue::Number(x) if x.is_finite() => Ok(*x),
+        other => Err(native_error(format!(
+            "'range' expects finite Numbers, got {other}."
+        ))),
+    };
+    let (start, end, step) = (number(&args[0])?, number(&args[1])?, number(&args[2])?);
+    LoxRange::float(start, end, step)
+        .map(LiteralValue::Range)
+        .map_err(native_error)
+}
+
+/// Converts a number or numeric string to an Int, truncating floats
+/// towards zero.
+fn int(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    match &args[0] {
+        LiteralValue::Int(i) => Ok(LiteralValue::Int(*i)),
+        LiteralValue::Number(x) => {
+            float_to_int(x.trunc())
+                .map(LiteralValue::Int)
+                .ok_or_else(|| {
+                    (
+                        ErrorCode::IntegerOverflow,
+                        format!("Can't convert {x:?} to an Int, it is out of range."),
+                    )
+                })
+        }
+        LiteralValue::StringValue(s) => s
+            .trim()
+            .parse()
+            .map(LiteralValue::Int)
+            .map_err(|_| native_error(format!("Can't convert {s:?} to an Int."))),
+        other => Err(native_error(format!(
+            "'int' expects a Number or String, got {}.",
+            other.to_type()
+        ))),
+    }
+}
+
+fn float(_: &Interpreter, args: &[LiteralValue]) -> Result<LiteralValue, NativeError> {
+    match &args[0] {
+        LiteralValue::Int(i) => Ok(LiteralValue::Number(*i as f64)),
+        LiteralValue::Number(x) => Ok(LiteralValue::Number(*x)),
+        LiteralValue::StringValue(s) => s
+            .trim()
+            .parse()
+            .map(LiteralValue::Number)
+            .map_err(|_| native_error(format!("Can't convert {s:?} to a Float."))),
+        other => Err(native_error(format!(
+            "'float' expects a Number or String, got {}.",
+            other.to_type()
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_index_counts_negative_from_end() {
+        let index = |x: f64, allow_end| list_index(&LiteralValue::Number(x), 3, allow_end);
+
+        assert_eq!(index(0.0, false), Ok(0));
+        assert_eq!(index(-1.0, false), Ok(2));
+        assert_eq!(index(3.0, true), Ok(3));
+        assert_eq!(
+            index(3.0, false).unwrap_err().0,
+            ErrorCode::IndexOutOfBounds
+        );
+        assert_eq!(
+            index(-4.0, false).unwrap_err().0,
+            ErrorCode::IndexOutOfBounds
+        );
+        assert_eq!(index(1.5, false).unwrap_err().0, ErrorCode::InvalidIndex);
+    }
+}
@@ -1,12 +1,16 @@
+use std::rc::Rc;
+
 use crate::{
+    error::{ErrorCode, LoxError},
     expression::{Expr, LiteralValue},
-    scanner::{Token, TokenType},
+    scanner::{Span, Token, TokenType},
     statement::Stmt,
 };
 
 #[derive(Debug)]
 enum FunctionKind {
     Function,
+    Method,
 }
 
 #[derive(Debug)]
@@ -20,17 +24,16 @@ impl Parser {
         Self { tokens, current: 0 }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<LoxError>> {
         let mut stmts = vec![];
         let mut errors = vec![];
 
         while !self.is_at_end() {
-            let line = self.peek().line;
             let stmt = self.declaration();
             match stmt {
                 Ok(stmt) => stmts.push(stmt),
-                Err(msg) => {
-                    errors.push(format!("Line {line}: {msg}"));
+                Err(err) => {
+                    errors.push(err);
                     self.synchronize();
                 }
             }
@@ -39,11 +42,11 @@ impl Parser {
         if errors.is_empty() {
             Ok(stmts)
         } else {
-            Err(errors.join("\n"))
+            Err(errors)
         }
     }
 
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn statement(&mut self) -> Result<Stmt, LoxError> {
         if self.match_tokens(&[TokenType::For]) {
             self.for_statement()
         } else if self.match_tokens(&[TokenType::If]) {
@@ -52,6 +55,12 @@ impl Parser {
             self.print_statement()
         } else if self.match_tokens(&[TokenType::Return]) {
             self.return_statement()
+        } else if self.match_tokens(&[TokenType::Break, TokenType::Continue]) {
+            self.loop_control_statement()
+        } else if self.match_tokens(&[TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.match_tokens(&[TokenType::Try]) {
+            self.try_statement()
         } else if self.match_tokens(&[TokenType::While]) {
             self.while_statement()
         } else if self.match_tokens(&[TokenType::LeftBrace]) {
@@ -61,9 +70,14 @@ impl Parser {
         }
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, String> {
+    fn for_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "Expected '(' after 'for'.")?;
 
+        if self.check(TokenType::Identifier) && self.check_next(TokenType::In) {
+            return self.for_in_statement(start);
+        }
+
         let initializer = if self.match_tokens(&[TokenType::Semicolon]) {
             None
         } else if self.match_tokens(&[TokenType::Var]) {
@@ -72,9 +86,10 @@ impl Parser {
             Some(self.expression_statement()?)
         };
 
-        let condition = if self.match_tokens(&[TokenType::Semicolon]) {
+        let condition = if self.check(TokenType::Semicolon) {
             Expr::Literal {
                 value: LiteralValue::True,
+                span: self.peek().span,
             }
         } else {
             self.expression()?
@@ -92,34 +107,48 @@ impl Parser {
             "Expected ')' after for loop clauses.",
         )?;
 
-        let mut body = self.statement()?;
-
-        if let Some(increment_stmt) = increment {
-            body = Stmt::Block {
-                statements: vec![
-                    body,
-                    Stmt::Expression {
-                        expression: increment_stmt,
-                    },
-                ],
-            }
-        };
+        let body = self.statement()?;
+        let span = self.span_from(start);
 
-        body = Stmt::While {
+        let mut body = Stmt::While {
             condition,
             body: Box::new(body),
+            increment,
+            span,
         };
 
         if let Some(initializer_stmt) = initializer {
             body = Stmt::Block {
                 statements: vec![initializer_stmt, body],
+                span,
             }
         };
 
         Ok(body)
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, String> {
+    /// Parses the rest of `for (name in iterable) body`, after the '('.
+    fn for_in_statement(&mut self, start: Span) -> Result<Stmt, LoxError> {
+        let name = self.advance();
+        let keyword = self.advance();
+        let iterable = self.expression()?;
+        self.consume(
+            TokenType::RightParen,
+            "Expected ')' after for loop iterable.",
+        )?;
+
+        let body = self.statement()?;
+        Ok(Stmt::ForIn {
+            name,
+            keyword,
+            iterable,
+            body: Box::new(body),
+            span: self.span_from(start),
+        })
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "Expected '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expected ')' after 'if'.")?;
@@ -136,16 +165,21 @@ impl Parser {
             condition,
             then_stmt,
             else_stmt,
+            span: self.span_from(start),
         })
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, String> {
+    fn print_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';' after value.")?;
-        Ok(Stmt::Print { expression: value })
+        Ok(Stmt::Print {
+            expression: value,
+            span: self.span_from(start),
+        })
     }
 
-    fn return_statement(&mut self) -> Result<Stmt, String> {
+    fn return_statement(&mut self) -> Result<Stmt, LoxError> {
         let keyword = self.previous();
 
         let value = if !self.check(TokenType::Semicolon) {
@@ -155,16 +189,89 @@ impl Parser {
         };
 
         self.consume(TokenType::Semicolon, "Expect ';' after return value")?;
-        Ok(Stmt::Return { keyword, value })
+        Ok(Stmt::Return {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        })
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expected ';' after thrown value.")?;
+        Ok(Stmt::Throw {
+            span: self.span_from(keyword.span),
+            keyword,
+            value,
+        })
+    }
+
+    fn try_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        self.consume(TokenType::LeftBrace, "Expected '{' after 'try'.")?;
+        let body = Box::new(self.block_statement()?);
+
+        let catch = if self.match_tokens(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expected '(' after 'catch'.")?;
+            let name = self.consume(TokenType::Identifier, "Expected error variable name.")?;
+            self.consume(TokenType::RightParen, "Expected ')' after error variable.")?;
+            self.consume(TokenType::LeftBrace, "Expected '{' after catch clause.")?;
+            Some((name, Box::new(self.block_statement()?)))
+        } else {
+            None
+        };
+
+        let finally = if self.match_tokens(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expected '{' after 'finally'.")?;
+            Some(Box::new(self.block_statement()?))
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(LoxError::parse(
+                ErrorCode::ExpectedToken,
+                &self.peek(),
+                "Expected 'catch' or 'finally' after try block.",
+            ));
+        }
+
+        Ok(Stmt::Try {
+            body,
+            catch,
+            finally,
+            span: self.span_from(keyword.span),
+        })
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt, String> {
+    fn loop_control_statement(&mut self) -> Result<Stmt, LoxError> {
+        let keyword = self.previous();
+        self.consume(
+            TokenType::Semicolon,
+            &format!("Expected ';' after '{}'.", keyword.lexeme),
+        )?;
+
+        let span = self.span_from(keyword.span);
+        if keyword.token_type == TokenType::Break {
+            Ok(Stmt::Break { keyword, span })
+        } else {
+            Ok(Stmt::Continue { keyword, span })
+        }
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.peek().span;
         let value = self.expression()?;
         self.consume(TokenType::Semicolon, "Expected ';' after value.")?;
-        Ok(Stmt::Expression { expression: value })
+        Ok(Stmt::Expression {
+            expression: value,
+            span: self.span_from(start),
+        })
     }
 
-    fn block_statement(&mut self) -> Result<Stmt, String> {
+    fn block_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         let mut statements: Vec<Stmt> = vec![];
 
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
@@ -173,38 +280,127 @@ impl Parser {
         }
 
         match self.consume(TokenType::RightBrace, "Expected '}' after a block") {
-            Ok(_) => Ok(Stmt::Block { statements }),
-            Err(msg) => Err(msg),
+            Ok(_) => Ok(Stmt::Block {
+                statements,
+                span: self.span_from(start),
+            }),
+            Err(err) => Err(err),
         }
     }
 
-    fn assignment(&mut self) -> Result<Expr, String> {
-        let expr = self.or()?;
+    fn assignment(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.conditional()?;
+
+        if self.match_tokens(&[
+            TokenType::PlusEqual,
+            TokenType::MinusEqual,
+            TokenType::StarEqual,
+            TokenType::SlashEqual,
+            TokenType::PercentEqual,
+        ]) {
+            let operator = self.previous();
+            let value = self.assignment()?;
+            let span = expr.span().to(value.span());
+            return self.update(expr, operator, value, false, span);
+        }
 
         if self.match_tokens(&[TokenType::Equal]) {
             let equals = self.previous();
             let value = self.expression()?;
+            let span = expr.span().to(value.span());
 
             match expr {
-                Expr::Variable { name } => Ok(Expr::Assign {
+                Expr::Variable { id: _, name, .. } => Ok(Expr::Assign {
+                    id: Expr::next_id(),
                     name,
                     value: Box::from(value),
+                    span,
                 }),
-                _ => Err(format!("{equals:?}: Invalid Assignment target")),
+                Expr::Get {
+                    object,
+                    name,
+                    optional: false,
+                    ..
+                } => Ok(Expr::Set {
+                    object,
+                    name,
+                    value: Box::from(value),
+                    span,
+                }),
+                Expr::Index {
+                    object,
+                    bracket,
+                    index,
+                    ..
+                } => Ok(Expr::IndexSet {
+                    object,
+                    bracket,
+                    index,
+                    value: Box::from(value),
+                    span,
+                }),
+                _ => Err(LoxError::parse(
+                    ErrorCode::InvalidAssignmentTarget,
+                    &equals,
+                    "Invalid Assignment target",
+                )
+                .with_help("only variables, properties and indexed elements can be assigned to")),
             }
         } else {
             Ok(expr)
         }
     }
 
-    fn lambda_expression(&mut self) -> Result<Expr, String> {
+    /// Builds a compound assignment or increment of `target`, which must be
+    /// something `=` could assign to.
+    fn update(
+        &self,
+        target: Expr,
+        operator: Token,
+        value: Expr,
+        postfix: bool,
+        span: Span,
+    ) -> Result<Expr, LoxError> {
+        match target {
+            Expr::Variable { .. }
+            | Expr::Get {
+                optional: false, ..
+            }
+            | Expr::Index { .. } => Ok(Expr::Update {
+                span,
+                target: Box::new(target),
+                operator,
+                value: Box::new(value),
+                postfix,
+            }),
+            _ => Err(LoxError::parse(
+                ErrorCode::InvalidAssignmentTarget,
+                &operator,
+                &format!("Invalid target for '{}'", operator.lexeme),
+            )
+            .with_help("only variables, properties and indexed elements can be updated")),
+        }
+    }
+
+    /// The implicit `1` that `++` and `--` add or subtract.
+    fn one(operator: &Token) -> Expr {
+        Expr::Literal {
+            value: LiteralValue::Int(1),
+            span: operator.span,
+        }
+    }
+
+    fn lambda_expression(&mut self) -> Result<Expr, LoxError> {
+        let start = self.previous().span;
         let paren = self.consume(TokenType::LeftParen, "Expected '(' after lambda function.")?;
         let mut params = vec![];
 
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
-                    return Err(String::from(
+                    return Err(LoxError::parse(
+                        ErrorCode::TooManyArguments,
+                        &self.peek(),
                         "Can't have more than 255 arguments in a lambda function.",
                     ));
                 }
@@ -228,24 +424,66 @@ impl Parser {
         )?;
 
         let body = match self.block_statement()? {
-            Stmt::Block { statements } => statements,
+            Stmt::Block { statements, .. } => statements,
             _ => panic!("Block statement parsed something that was not a block."),
         };
 
         Ok(Expr::Lambda {
             paren,
             params,
-            body,
+            body: Rc::new(body),
+            span: self.span_from(start),
         })
     }
 
-    fn or(&mut self) -> Result<Expr, String> {
+    /// Parses `cond ? a : b`, which is right-associative so that
+    /// `a ? b : c ? d : e` groups as `a ? b : (c ? d : e)`.
+    fn conditional(&mut self) -> Result<Expr, LoxError> {
+        let condition = self.nil_coalesce()?;
+
+        if self.match_tokens(&[TokenType::Question]) {
+            let then_expr = self.expression()?;
+            self.consume(
+                TokenType::Colon,
+                "Expected ':' after the true branch of a conditional.",
+            )?;
+            let else_expr = self.conditional()?;
+            Ok(Expr::Conditional {
+                span: condition.span().to(else_expr.span()),
+                condition: Box::new(condition),
+                then_expr: Box::new(then_expr),
+                else_expr: Box::new(else_expr),
+            })
+        } else {
+            Ok(condition)
+        }
+    }
+
+    fn nil_coalesce(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.or()?;
+
+        while self.match_tokens(&[TokenType::QuestionQuestion]) {
+            let operator = self.previous();
+            let right = self.or()?;
+            expr = Expr::Logical {
+                span: expr.span().to(right.span()),
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+            };
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.and()?;
 
         while self.match_tokens(&[TokenType::Or]) {
             let operator = self.previous();
             let right = self.and()?;
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -255,13 +493,14 @@ impl Parser {
         Ok(expr)
     }
 
-    fn and(&mut self) -> Result<Expr, String> {
+    fn and(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.equality()?;
 
         while self.match_tokens(&[TokenType::And]) {
             let operator = self.previous();
             let right = self.equality()?;
             expr = Expr::Logical {
+                span: expr.span().to(right.span()),
                 left: Box::new(expr),
                 operator,
                 right: Box::new(right),
@@ -271,7 +510,11 @@ impl Parser {
         Ok(expr)
     }
 
-    fn fun_declaration(&mut self, kind: FunctionKind) -> Result<Stmt, String> {
+    fn fun_declaration(&mut self, kind: FunctionKind) -> Result<Stmt, LoxError> {
+        let start = match kind {
+            FunctionKind::Function => self.previous().span,
+            FunctionKind::Method => self.peek().span,
+        };
         let name = self.consume(TokenType::Identifier, &format!("Expected {kind:?} name."))?;
 
         self.consume(
@@ -283,7 +526,11 @@ impl Parser {
         if !self.check(TokenType::RightParen) {
             loop {
                 if params.len() >= 255 {
-                    return Err(String::from("Can't have more than 255 parameters."));
+                    return Err(LoxError::parse(
+                        ErrorCode::TooManyArguments,
+                        &self.peek(),
+                        "Can't have more than 255 parameters.",
+                    ));
                 }
 
                 params.push(self.consume(TokenType::Identifier, "Expected parameter name.")?);
@@ -299,18 +546,56 @@ impl Parser {
             TokenType::LeftBrace,
             &format!("Expect '{{' before {kind:?} body."),
         )?;
-        println!("Here in parser");
         let body = match self.block_statement()? {
-            Stmt::Block { statements } => statements,
+            Stmt::Block { statements, .. } => statements,
             _ => panic!("Found something other than a block"),
         };
 
-        let s = Stmt::Function { name, params, body };
+        let s = Stmt::Function {
+            name,
+            params,
+            body: Rc::new(body),
+            span: self.span_from(start),
+        };
 
         Ok(s)
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
+    fn class_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
+        let name = self.consume(TokenType::Identifier, "Expected class name.")?;
+
+        let superclass = if self.match_tokens(&[TokenType::Less]) {
+            let superclass_name =
+                self.consume(TokenType::Identifier, "Expected superclass name.")?;
+            Some(Expr::Variable {
+                id: Expr::next_id(),
+                span: superclass_name.span,
+                name: superclass_name,
+            })
+        } else {
+            None
+        };
+
+        self.consume(TokenType::LeftBrace, "Expected '{' before class body.")?;
+
+        let mut methods = vec![];
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.fun_declaration(FunctionKind::Method)?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expected '}' after class body.")?;
+
+        Ok(Stmt::Class {
+            name,
+            superclass,
+            methods,
+            span: self.span_from(start),
+        })
+    }
+
+    fn var_declaration(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         let name = self.consume(TokenType::Identifier, "Expected variable name.")?;
 
         let initializer = if self.match_tokens(&[TokenType::Equal]) {
@@ -318,6 +603,7 @@ impl Parser {
         } else {
             Expr::Literal {
                 value: LiteralValue::Nil,
+                span: name.span,
             }
         };
 
@@ -326,10 +612,15 @@ impl Parser {
             "Expected ';' after variable declaration.",
         )?;
 
-        Ok(Stmt::Var { name, initializer })
+        Ok(Stmt::Var {
+            name,
+            initializer,
+            span: self.span_from(start),
+        })
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, String> {
+    fn while_statement(&mut self) -> Result<Stmt, LoxError> {
+        let start = self.previous().span;
         self.consume(TokenType::LeftParen, "Expect '(' after a 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after while condition.")?;
@@ -338,15 +629,19 @@ impl Parser {
         Ok(Stmt::While {
             condition,
             body: Box::new(body),
+            increment: None,
+            span: self.span_from(start),
         })
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
+    fn expression(&mut self) -> Result<Expr, LoxError> {
         self.assignment()
     }
 
-    fn declaration(&mut self) -> Result<Stmt, String> {
-        if self.match_tokens(&[TokenType::Fun]) {
+    fn declaration(&mut self) -> Result<Stmt, LoxError> {
+        if self.match_tokens(&[TokenType::Class]) {
+            self.class_declaration()
+        } else if self.match_tokens(&[TokenType::Fun]) {
             self.fun_declaration(FunctionKind::Function)
         } else if self.match_tokens(&[TokenType::Var]) {
             self.var_declaration()
@@ -355,13 +650,14 @@ impl Parser {
         }
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.comparison()?;
 
         while self.match_tokens(&[TokenType::BangEqual, TokenType::EqualEqual]) {
             let operator = self.previous();
             let right = self.comparison()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -371,8 +667,8 @@ impl Parser {
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let mut expr = self.term()?;
+    fn comparison(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bit_or()?;
 
         while self.match_tokens(&[
             TokenType::Greater,
@@ -380,9 +676,80 @@ impl Parser {
             TokenType::Less,
             TokenType::LessEqual,
         ]) {
+            let operator = self.previous();
+            let right = self.bit_or()?;
+            expr = Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::from(expr),
+                operator,
+                right: Box::from(right),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    // The bitwise operators bind tighter than comparisons, so that
+    // `flags & MASK == 0` compares the masked value rather than `MASK == 0`.
+    fn bit_or(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bit_xor()?;
+
+        while self.match_tokens(&[TokenType::Pipe]) {
+            let operator = self.previous();
+            let right = self.bit_xor()?;
+            expr = Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::from(expr),
+                operator,
+                right: Box::from(right),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_xor(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.bit_and()?;
+
+        while self.match_tokens(&[TokenType::Caret]) {
+            let operator = self.previous();
+            let right = self.bit_and()?;
+            expr = Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::from(expr),
+                operator,
+                right: Box::from(right),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn bit_and(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.shift()?;
+
+        while self.match_tokens(&[TokenType::Ampersand]) {
+            let operator = self.previous();
+            let right = self.shift()?;
+            expr = Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::from(expr),
+                operator,
+                right: Box::from(right),
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn shift(&mut self) -> Result<Expr, LoxError> {
+        let mut expr = self.term()?;
+
+        while self.match_tokens(&[TokenType::LessLess, TokenType::GreaterGreater]) {
             let operator = self.previous();
             let right = self.term()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -392,13 +759,14 @@ impl Parser {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.factor()?;
 
         while self.match_tokens(&[TokenType::Minus, TokenType::Plus]) {
             let operator = self.previous();
             let right = self.factor()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -408,13 +776,19 @@ impl Parser {
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.unary()?;
 
-        while self.match_tokens(&[TokenType::Slash, TokenType::Star]) {
+        while self.match_tokens(&[
+            TokenType::Slash,
+            TokenType::Star,
+            TokenType::Percent,
+            TokenType::Div,
+        ]) {
             let operator = self.previous();
             let right = self.unary()?;
             expr = Expr::Binary {
+                span: expr.span().to(right.span()),
                 left: Box::from(expr),
                 operator,
                 right: Box::from(right),
@@ -424,20 +798,57 @@ impl Parser {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
-        if self.match_tokens(&[TokenType::Bang, TokenType::Minus]) {
+    fn unary(&mut self) -> Result<Expr, LoxError> {
+        if self.match_tokens(&[TokenType::Bang, TokenType::Minus, TokenType::Tilde]) {
             let operator = self.previous();
             let right = self.unary()?;
             Ok(Expr::Unary {
+                span: operator.span.to(right.span()),
                 operator,
                 right: Box::from(right),
             })
+        } else if self.match_tokens(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            let target = self.unary()?;
+            let span = operator.span.to(target.span());
+            self.update(target, operator.clone(), Self::one(&operator), false, span)
         } else {
-            self.call()
+            self.power()
         }
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
+    /// Parses `**`, which is right-associative and binds tighter than a
+    /// unary operator on its left, so `-2 ** 2` is `-(2 ** 2)`.
+    fn power(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.postfix()?;
+
+        if self.match_tokens(&[TokenType::StarStar]) {
+            let operator = self.previous();
+            let right = self.unary()?;
+            Ok(Expr::Binary {
+                span: expr.span().to(right.span()),
+                left: Box::from(expr),
+                operator,
+                right: Box::from(right),
+            })
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn postfix(&mut self) -> Result<Expr, LoxError> {
+        let expr = self.call()?;
+
+        if self.match_tokens(&[TokenType::PlusPlus, TokenType::MinusMinus]) {
+            let operator = self.previous();
+            let span = expr.span().to(operator.span);
+            self.update(expr, operator.clone(), Self::one(&operator), true, span)
+        } else {
+            Ok(expr)
+        }
+    }
+
+    fn finish_call(&mut self, callee: Expr, optional: bool) -> Result<Expr, LoxError> {
         let mut arguments: Vec<Expr> = vec![];
 
         if !self.check(TokenType::RightParen) {
@@ -446,7 +857,9 @@ impl Parser {
 
                 if arguments.len() >= 255 {
                     // Change to handle gracefully if ever implemented
-                    return Err(String::from(
+                    return Err(LoxError::parse(
+                        ErrorCode::TooManyArguments,
+                        &self.peek(),
                         "Functions cannot have more than 255 arguments",
                     ));
                 }
@@ -459,18 +872,51 @@ impl Parser {
 
         let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
         Ok(Expr::Call {
+            span: callee.span().to(paren.span),
             callee: Box::new(callee),
             arguments,
+            optional,
             paren,
         })
     }
 
-    fn call(&mut self) -> Result<Expr, String> {
+    fn call(&mut self) -> Result<Expr, LoxError> {
         let mut expr = self.primary()?;
 
         loop {
             if self.match_tokens(&[TokenType::LeftParen]) {
-                expr = self.finish_call(expr)?;
+                expr = self.finish_call(expr, false)?;
+            } else if self.match_tokens(&[TokenType::Dot]) {
+                let name =
+                    self.consume(TokenType::Identifier, "Expected property name after '.'.")?;
+                expr = Expr::Get {
+                    span: expr.span().to(name.span),
+                    object: Box::new(expr),
+                    name,
+                    optional: false,
+                };
+            } else if self.match_tokens(&[TokenType::QuestionDot]) {
+                if self.match_tokens(&[TokenType::LeftParen]) {
+                    expr = self.finish_call(expr, true)?;
+                } else {
+                    let name =
+                        self.consume(TokenType::Identifier, "Expected property name after '?.'.")?;
+                    expr = Expr::Get {
+                        span: expr.span().to(name.span),
+                        object: Box::new(expr),
+                        name,
+                        optional: true,
+                    };
+                }
+            } else if self.match_tokens(&[TokenType::LeftBracket]) {
+                let index = self.expression()?;
+                let bracket = self.consume(TokenType::RightBracket, "Expected ']' after index.")?;
+                expr = Expr::Index {
+                    span: expr.span().to(bracket.span),
+                    object: Box::new(expr),
+                    bracket,
+                    index: Box::new(index),
+                };
             } else {
                 break;
             }
@@ -479,7 +925,7 @@ impl Parser {
         Ok(expr)
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, LoxError> {
         let token = self.peek();
 
         let result = match token.token_type {
@@ -489,6 +935,7 @@ impl Parser {
                 self.consume(TokenType::RightParen, "Expected ')'")?;
                 Expr::Grouping {
                     expression: Box::from(expr),
+                    span: self.span_from(token.span),
                 }
             }
             TokenType::False
@@ -498,25 +945,141 @@ impl Parser {
             | TokenType::StringLit => {
                 self.advance();
                 Expr::Literal {
+                    span: token.span,
                     value: LiteralValue::from_token(token),
                 }
             }
+            TokenType::Interpolation => {
+                self.advance();
+                self.interpolation()?
+            }
+            TokenType::LeftBracket => {
+                self.advance();
+                let mut elements = vec![];
+                while !self.check(TokenType::RightBracket) {
+                    elements.push(self.expression()?);
+                    if !self.match_tokens(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightBracket, "Expected ']' after list elements.")?;
+                Expr::List {
+                    elements,
+                    span: self.span_from(token.span),
+                }
+            }
+            // Statements starting with `{` are blocks, so here it opens a map.
+            TokenType::LeftBrace => {
+                self.advance();
+                let mut entries = vec![];
+                while !self.check(TokenType::RightBrace) {
+                    let key = self.expression()?;
+                    self.consume(TokenType::Colon, "Expected ':' after map key.")?;
+                    let value = self.expression()?;
+                    entries.push((key, value));
+                    if !self.match_tokens(&[TokenType::Comma]) {
+                        break;
+                    }
+                }
+                self.consume(TokenType::RightBrace, "Expected '}' after map entries.")?;
+                Expr::Map {
+                    span: self.span_from(token.span),
+                    brace: token,
+                    entries,
+                }
+            }
+            TokenType::Super => {
+                self.advance();
+                let keyword = self.previous();
+                self.consume(TokenType::Dot, "Expected '.' after 'super'.")?;
+                let method =
+                    self.consume(TokenType::Identifier, "Expected superclass method name.")?;
+                Expr::Super {
+                    id: Expr::next_id(),
+                    span: keyword.span.to(method.span),
+                    keyword,
+                    method,
+                }
+            }
+            TokenType::This => {
+                self.advance();
+                Expr::This {
+                    id: Expr::next_id(),
+                    keyword: self.previous(),
+                    span: token.span,
+                }
+            }
             TokenType::Identifier => {
                 self.advance();
                 Expr::Variable {
+                    id: Expr::next_id(),
                     name: self.previous(),
+                    span: token.span,
                 }
             }
             TokenType::Fun => {
                 self.advance();
                 self.lambda_expression()?
             }
-            other => return Err(format!("Expected an expression, got {other:?}.")),
+            other => {
+                return Err(LoxError::parse(
+                    ErrorCode::ExpectedExpression,
+                    &token,
+                    &format!("Expected an expression, got {other:?}."),
+                ))
+            }
         };
 
         Ok(result)
     }
 
+    /// Parses the rest of an interpolated string whose first segment was
+    /// just consumed. Each embedded expression is followed by another segment.
+    fn interpolation(&mut self) -> Result<Expr, LoxError> {
+        let start = self.previous().span;
+        let mut parts = vec![];
+        let mut segment = self.previous();
+
+        loop {
+            let value = LiteralValue::from_token(segment.clone());
+            if !matches!(&value, LiteralValue::StringValue(s) if s.is_empty()) {
+                parts.push(Expr::Literal {
+                    span: segment.span,
+                    value,
+                });
+            }
+            if segment.token_type == TokenType::StringLit {
+                break;
+            }
+
+            parts.push(self.expression()?);
+
+            segment = self.peek();
+            match segment.token_type {
+                TokenType::Interpolation | TokenType::StringLit => {
+                    self.advance();
+                }
+                _ => {
+                    return Err(LoxError::parse(
+                        ErrorCode::ExpectedToken,
+                        &segment,
+                        "Expected '}' after interpolated expression.",
+                    ))
+                }
+            }
+        }
+
+        Ok(Expr::Interpolation {
+            parts,
+            span: self.span_from(start),
+        })
+    }
+
+    /// Returns a span from `start` to the end of the last consumed token.
+    fn span_from(&self, start: Span) -> Span {
+        start.to(self.previous().span)
+    }
+
     fn match_tokens(&mut self, types: &[TokenType]) -> bool {
         for t in types {
             if self.check(*t) {
@@ -527,13 +1090,13 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, String> {
+    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, LoxError> {
         let token = self.peek();
         if token.token_type == token_type {
             self.advance();
             Ok(self.previous())
         } else {
-            Err(String::from(message)) // TODO: Adjust parameters to take String
+            Err(LoxError::parse(ErrorCode::ExpectedToken, &token, message))
         }
     }
 
@@ -545,6 +1108,14 @@ impl Parser {
         }
     }
 
+    /// Like `check`, but for the token after the current one.
+    fn check_next(&self, token_type: TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type == token_type,
+            None => false,
+        }
+    }
+
     fn advance(&mut self) -> Token {
         if !self.is_at_end() {
             self.current += 1
@@ -580,7 +1151,11 @@ impl Parser {
                 | TokenType::If
                 | TokenType::While
                 | TokenType::Print
-                | TokenType::Return => return,
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try
+                | TokenType::Break
+                | TokenType::Continue => return,
                 _ => (),
             }
 
@@ -599,32 +1174,32 @@ mod tests {
         let one = Token {
             token_type: TokenType::Number,
             lexeme: String::from("1"),
-            literal: Some(TokenLiteral::FValue(1.0)),
-            line: 0,
+            literal: Some(TokenLiteral::IValue(1)),
+            span: Span::default(),
         };
         let plus = Token {
             token_type: TokenType::Plus,
-            lexeme: String::from("+".to_string()),
+            lexeme: String::from("+"),
             literal: None,
-            line: 0,
+            span: Span::default(),
         };
         let two = Token {
             token_type: TokenType::Number,
             lexeme: String::from("2"),
-            literal: Some(TokenLiteral::FValue(2.0)),
-            line: 0,
+            literal: Some(TokenLiteral::IValue(2)),
+            span: Span::default(),
         };
         let semicolon = Token {
             token_type: TokenType::Semicolon,
             lexeme: String::from(";"),
             literal: None,
-            line: 0,
+            span: Span::default(),
         };
         let eof = Token {
             token_type: TokenType::Eof,
             lexeme: String::from(""),
             literal: None,
-            line: 0,
+            span: Span::default(),
         };
 
         let tokens = vec![one, plus, two, semicolon, eof];
@@ -659,4 +1234,169 @@ mod tests {
 
         assert_eq!(string_expr, "(>= 1 (group (+ 3 4)))");
     }
+
+    #[test]
+    fn test_property_assignment() {
+        let source = "a.b.c = this.d;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let parsed_expr = parser.parse().unwrap();
+        let string_expr = parsed_expr[0].to_string();
+
+        assert_eq!(string_expr, "(set (get (var a) b) c (get this d))");
+    }
+
+    #[test]
+    fn test_superclass_and_super_call() {
+        let source = "class B < A { f() { return super.f(); } }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let parsed_expr = parser.parse().unwrap();
+        let string_expr = parsed_expr[0].to_string();
+
+        assert_eq!(
+            string_expr,
+            "(class B < (var A) [(fun f [] [(-> ((super f) []))])])"
+        );
+    }
+
+    #[test]
+    fn test_node_spans() {
+        let source = "var a = 1;\nprint a + 22;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].span().start..stmts[0].span().end, 0..10);
+        assert_eq!(stmts[1].span().line, 2);
+        assert_eq!(stmts[1].span().start..stmts[1].span().end, 11..24);
+        match &stmts[1] {
+            Stmt::Print { expression, .. } => {
+                let span = expression.span();
+                assert_eq!(span.start..span.end, 17..23);
+                assert_eq!(span.column, 7);
+            }
+            _ => panic!("expected a print statement"),
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let source = r#""${a}+${"b${c}"}!";"#;
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(interp (var a) + (interp b (var c)) !)"
+        );
+    }
+
+    #[test]
+    fn test_list_index_assignment() {
+        let source = "xs[0] = [1, 2,][-1];";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(index-set (var xs) 0 (index (list 1 2) (- 1)))"
+        );
+    }
+
+    #[test]
+    fn test_for_in_loop() {
+        let source = "for (x in xs) print x;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(for x in (var xs) do (print (var x)))"
+        );
+    }
+
+    #[test]
+    fn test_try_catch_finally() {
+        let source = "try { throw 1; } catch (e) { print e; } finally { print 2; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(try (block (throw 1)) catch e (block (print (var e))) finally (block (print 2)))"
+        );
+    }
+
+    #[test]
+    fn test_conditional_and_nil_operators() {
+        let source = "a ? b ?? c : d ? e : f; x?.y?.();";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(?: (var a) (?? (var b) (var c)) (?: (var d) (var e) (var f)))"
+        );
+        assert_eq!(stmts[1].to_string(), "((get? (var x) y)?. [])");
+    }
+
+    #[test]
+    fn test_compound_assignment_and_increments() {
+        let source = "a.b += c -= 2; ++xs[0]; i--;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(stmts[0].to_string(), "(+= (get (var a) b) (-= (var c) 2))");
+        assert_eq!(stmts[1].to_string(), "(++ (index (var xs) 0))");
+        assert_eq!(stmts[2].to_string(), "((var i) --)");
+    }
+
+    #[test]
+    fn test_arithmetic_and_bitwise_precedence() {
+        let source = "-a ** b ** c; a | b ^ c & d << e + f div g; x % y < ~z;";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(- (** (var a) (** (var b) (var c))))"
+        );
+        assert_eq!(
+            stmts[1].to_string(),
+            "(| (var a) (^ (var b) (& (var c) (<< (var d) (+ (var e) (div (var f) (var g)))))))"
+        );
+        assert_eq!(stmts[2].to_string(), "(< (% (var x) (var y)) (~ (var z)))");
+    }
+
+    #[test]
+    fn test_map_literal_in_expression_position() {
+        let source = "{ var m = {\"a\": 1, 2: {},}; }";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+
+        assert_eq!(
+            stmts[0].to_string(),
+            "(block (var m (map (a 1) (2 (map )))))"
+        );
+    }
 }
@@ -0,0 +1,146 @@
+use crate::expression::LiteralValue;
+
+/// The value `range(start, end, step)` returns. Its items are worked out one
+/// at a time as a loop asks for them, so a huge range costs nothing until it
+/// is walked.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoxRange {
+    /// Ints from `start` up to but not including `end`.
+    Int { start: i64, end: i64, step: i64 },
+    /// The `count` Floats `start + i * step`. Multiplying instead of
+    /// accumulating keeps rounding errors from growing.
+    Float {
+        start: f64,
+        end: f64,
+        step: f64,
+        count: u64,
+    },
+}
+
+impl LoxRange {
+    pub fn int(start: i64, end: i64, step: i64) -> Result<Self, String> {
+        if step == 0 {
+            return Err(String::from("'range' step can't be zero."));
+        }
+        Ok(LoxRange::Int { start, end, step })
+    }
+
+    pub fn float(start: f64, end: f64, step: f64) -> Result<Self, String> {
+        if step == 0.0 {
+            return Err(String::from("'range' step can't be zero."));
+        }
+
+        let count = ((end - start) / step).ceil().max(0.0);
+        // `u64::MAX as f64` rounds up to 2^64, which is out of range.
+        if count >= u64::MAX as f64 {
+            return Err(format!(
+                "'range' from {start:?} to {end:?} by {step:?} has too many items."
+            ));
+        }
+        // Rounding can leave the last item at or past `end`, as
+        // `(1.1 - 1.0) / 0.1` is just over 1. This counts down in integers,
+        // since a large `count - 1.0` can round back to `count`.
+        let mut count = count as u64;
+        let past_end = |x: f64| if step > 0.0 { x >= end } else { x <= end };
+        while count > 0 && past_end(start + (count - 1) as f64 * step) {
+            count -= 1;
+        }
+
+        Ok(LoxRange::Float {
+            start,
+            end,
+            step,
+            count,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        match *self {
+            LoxRange::Int { start, end, step } => {
+                let (start, end, step) = (start as i128, end as i128, step as i128);
+                let distance = if step > 0 { end - start } else { start - end };
+                if distance <= 0 {
+                    0
+                } else {
+                    // Ceiling division, so a partial last step still counts.
+                    ((distance + step.abs() - 1) / step.abs()) as u64
+                }
+            }
+            LoxRange::Float { count, .. } => count,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The item at `index`, or `None` past the end.
+    pub fn get(&self, index: u64) -> Option<LiteralValue> {
+        if index >= self.len() {
+            return None;
+        }
+        match *self {
+            // Every item before the end lies between `start` and `end`, so
+            // it fits in an i64.
+            LoxRange::Int { start, step, .. } => Some(LiteralValue::Int(
+                (start as i128 + index as i128 * step as i128) as i64,
+            )),
+            LoxRange::Float { start, step, .. } => {
+                Some(LiteralValue::Number(start + index as f64 * step))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for LoxRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoxRange::Int { start, end, step } => write!(f, "range({start}, {end}, {step})"),
+            LoxRange::Float {
+                start, end, step, ..
+            } => write!(f, "range({start:?}, {end:?}, {step:?})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_items_without_building_them() {
+        let range = LoxRange::int(0, 10, 3).unwrap();
+        assert_eq!(range.len(), 4);
+        assert_eq!(range.get(3), Some(LiteralValue::Int(9)));
+        assert_eq!(range.get(4), None);
+
+        assert_eq!(LoxRange::int(3, 0, -1).unwrap().len(), 3);
+        assert_eq!(LoxRange::int(0, 3, -1).unwrap().len(), 0);
+        assert_eq!(
+            LoxRange::int(i64::MIN, i64::MAX, 1).unwrap().len(),
+            u64::MAX
+        );
+        assert_eq!(LoxRange::float(0.0, 1.0, 0.25).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn exclude_end_despite_rounding() {
+        let range = LoxRange::float(1.0, 1.1, 0.1).unwrap();
+        assert_eq!(range.len(), 1);
+        assert_eq!(range.get(0), Some(LiteralValue::Number(1.0)));
+
+        let range = LoxRange::float(0.0, 0.3, 0.1).unwrap();
+        assert_eq!(range.len(), 3);
+        assert_eq!(range.get(2), Some(LiteralValue::Number(0.2)));
+
+        assert_eq!(LoxRange::float(0.3, 0.0, -0.1).unwrap().len(), 3);
+        assert!(LoxRange::float(0.0, 1e18, 0.5).unwrap().len() < 2_000_000_000_000_000_000);
+    }
+
+    #[test]
+    fn reject_invalid_ranges() {
+        assert!(LoxRange::int(0, 1, 0).is_err());
+        assert!(LoxRange::float(0.0, 1e300, 1.0).is_err());
+        assert!(LoxRange::float(0.0, f64::INFINITY, 1.0).is_err());
+    }
+}
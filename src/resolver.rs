@@ -0,0 +1,516 @@
+use std::collections::HashMap;
+
+use crate::error::{ErrorCode, LoxError};
+use crate::expression::Expr;
+use crate::scanner::Token;
+use crate::statement::Stmt;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum LoopType {
+    None,
+    Loop,
+}
+
+/// Walks the parsed program once before it runs, recording how many scopes
+/// separate each local variable use from its declaration.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, bool>>,
+    locals: HashMap<usize, usize>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    current_loop: LoopType,
+    errors: Vec<LoxError>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![],
+            locals: HashMap::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            current_loop: LoopType::None,
+            errors: vec![],
+        }
+    }
+
+    /// Resolves `stmts`, returning the scope depth of every local variable
+    /// expression keyed by expression id, or every error found.
+    pub fn resolve(mut self, stmts: &[Stmt]) -> Result<HashMap<usize, usize>, Vec<LoxError>> {
+        self.resolve_stmts(stmts);
+
+        if self.errors.is_empty() {
+            Ok(self.locals)
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn resolve_stmts(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block { statements, .. } => {
+                self.begin_scope();
+                self.resolve_stmts(statements);
+                self.end_scope();
+            }
+            Stmt::Break { keyword, .. } | Stmt::Continue { keyword, .. } => {
+                if self.current_loop == LoopType::None {
+                    self.error(
+                        ErrorCode::InvalidLoopControl,
+                        keyword,
+                        &format!("Can't use '{}' outside of a loop.", keyword.lexeme),
+                    );
+                }
+            }
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => {
+                let enclosing_class = self.current_class;
+                self.current_class = ClassType::Class;
+
+                self.declare(name);
+                self.define(name);
+
+                if let Some(superclass) = superclass {
+                    if let Expr::Variable {
+                        name: superclass_name,
+                        ..
+                    } = superclass
+                    {
+                        if superclass_name.lexeme == name.lexeme {
+                            self.error(
+                                ErrorCode::SelfInheritance,
+                                superclass_name,
+                                "A class can't inherit from itself.",
+                            );
+                        }
+                    }
+
+                    self.current_class = ClassType::Subclass;
+                    self.resolve_expr(superclass);
+
+                    self.begin_scope();
+                    self.define_name("super");
+                }
+
+                self.begin_scope();
+                self.define_name("this");
+
+                for method in methods {
+                    if let Stmt::Function {
+                        name: method_name,
+                        params,
+                        body,
+                        ..
+                    } = method
+                    {
+                        let function_type = if method_name.lexeme == "init" {
+                            FunctionType::Initializer
+                        } else {
+                            FunctionType::Method
+                        };
+                        self.resolve_function(params, body, function_type);
+                    }
+                }
+
+                self.end_scope();
+                if superclass.is_some() {
+                    self.end_scope();
+                }
+
+                self.current_class = enclosing_class;
+            }
+            Stmt::Expression { expression, .. } => self.resolve_expr(expression),
+            Stmt::Function {
+                name, params, body, ..
+            } => {
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::If {
+                condition,
+                then_stmt,
+                else_stmt,
+                ..
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_stmt);
+                if let Some(else_stmt) = else_stmt {
+                    self.resolve_stmt(else_stmt);
+                }
+            }
+            Stmt::Print { expression, .. } => self.resolve_expr(expression),
+            Stmt::Return { keyword, value, .. } => {
+                if self.current_function == FunctionType::None {
+                    self.errors.push(
+                        LoxError::resolve(
+                            ErrorCode::ReturnOutsideFunction,
+                            keyword,
+                            "Can't return from top-level code.",
+                        )
+                        .with_help("'return' is only allowed inside a function or method"),
+                    );
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(
+                            ErrorCode::ReturnValueFromInitializer,
+                            keyword,
+                            "Can't return a value from an initializer.",
+                        );
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Throw { value, .. } => self.resolve_expr(value),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                self.resolve_stmt(body);
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_stmt(handler);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.resolve_stmt(finally);
+                }
+            }
+            Stmt::Var {
+                name, initializer, ..
+            } => {
+                self.declare(name);
+                self.resolve_expr(initializer);
+                self.define(name);
+            }
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                ..
+            } => {
+                self.resolve_expr(iterable);
+
+                let enclosing_loop = self.current_loop;
+                self.current_loop = LoopType::Loop;
+                self.begin_scope();
+                self.declare(name);
+                self.define(name);
+                self.resolve_stmt(body);
+                self.end_scope();
+                self.current_loop = enclosing_loop;
+            }
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => {
+                self.resolve_expr(condition);
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+
+                let enclosing_loop = self.current_loop;
+                self.current_loop = LoopType::Loop;
+                self.resolve_stmt(body);
+                self.current_loop = enclosing_loop;
+            }
+        }
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Assign {
+                id, name, value, ..
+            } => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, name);
+            }
+            Expr::Binary { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expr(callee);
+                for argument in arguments {
+                    self.resolve_expr(argument);
+                }
+            }
+            Expr::Conditional {
+                condition,
+                then_expr,
+                else_expr,
+                ..
+            } => {
+                self.resolve_expr(condition);
+                self.resolve_expr(then_expr);
+                self.resolve_expr(else_expr);
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::Grouping { expression, .. } => self.resolve_expr(expression),
+            Expr::Index { object, index, .. } => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::Interpolation { parts, .. } => {
+                for part in parts {
+                    self.resolve_expr(part);
+                }
+            }
+            Expr::Lambda { params, body, .. } => {
+                self.resolve_function(params, body, FunctionType::Function)
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Literal { .. } => (),
+            Expr::Map { entries, .. } => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Set { object, value, .. } => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::Super { id, keyword, .. } => {
+                match self.current_class {
+                    ClassType::None => self.error(
+                        ErrorCode::InvalidSuper,
+                        keyword,
+                        "Can't use 'super' outside of a class.",
+                    ),
+                    ClassType::Class => self.error(
+                        ErrorCode::InvalidSuper,
+                        keyword,
+                        "Can't use 'super' in a class with no superclass.",
+                    ),
+                    ClassType::Subclass => (),
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::This { id, keyword, .. } => {
+                if self.current_class == ClassType::None {
+                    self.error(
+                        ErrorCode::InvalidThis,
+                        keyword,
+                        "Can't use 'this' outside of a class.",
+                    );
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::Update { target, value, .. } => {
+                self.resolve_expr(target);
+                self.resolve_expr(value);
+            }
+            Expr::Unary { right, .. } => self.resolve_expr(right),
+            Expr::Variable { id, name, .. } => {
+                if let Some(scope) = self.scopes.last() {
+                    if scope.get(&name.lexeme) == Some(&false) {
+                        self.errors.push(
+                            LoxError::resolve(
+                                ErrorCode::ReadInOwnInitializer,
+                                name,
+                                "Can't read local variable in its own initializer.",
+                            )
+                            .with_help(
+                                "give the new variable a different name to read an outer one",
+                            ),
+                        );
+                    }
+                }
+                self.resolve_local(*id, name);
+            }
+        }
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        // A function body starts outside of any loop, even if it is declared in one.
+        let enclosing_loop = self.current_loop;
+        self.current_loop = LoopType::None;
+
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve_stmts(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.current_loop = enclosing_loop;
+    }
+
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.lexeme) {
+                self.locals.insert(id, depth);
+                return;
+            }
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let already_declared = match self.scopes.last_mut() {
+            Some(scope) => scope.insert(name.lexeme.clone(), false).is_some(),
+            None => false,
+        };
+
+        if already_declared {
+            self.error(
+                ErrorCode::DuplicateDeclaration,
+                name,
+                "Already a variable with this name in this scope.",
+            );
+        }
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(&name.lexeme);
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    fn error(&mut self, code: ErrorCode, token: &Token, message: &str) {
+        self.errors.push(LoxError::resolve(code, token, message));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+    use crate::scanner::Scanner;
+
+    fn resolve(source: &str) -> Result<HashMap<usize, usize>, Vec<LoxError>> {
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_tokens().unwrap();
+        let mut parser = Parser::new(tokens);
+        let stmts = parser.parse().unwrap();
+        Resolver::new().resolve(&stmts)
+    }
+
+    #[test]
+    fn resolve_local_depths() {
+        let locals = resolve("var a = 1; { var b = a; { print b; } }").unwrap();
+        let mut depths: Vec<usize> = locals.values().copied().collect();
+        depths.sort();
+
+        // `a` is global and left unresolved; `b` is read one scope up.
+        assert_eq!(depths, vec![1]);
+    }
+
+    #[test]
+    fn reject_read_in_own_initializer() {
+        let errors = resolve("{ var a = a; }").unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::ReadInOwnInitializer);
+    }
+
+    #[test]
+    fn reject_top_level_return() {
+        let errors = resolve("return 1;").unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::ReturnOutsideFunction);
+    }
+
+    #[test]
+    fn report_all_errors_in_one_batch() {
+        let errors = resolve("fun f(a, a) { var b; var b; }\nreturn;").unwrap_err();
+
+        assert_eq!(errors.len(), 3);
+        assert_eq!(errors[0].line(), 1);
+        assert_eq!(errors[0].code(), ErrorCode::DuplicateDeclaration);
+        assert_eq!(errors[1].code(), ErrorCode::DuplicateDeclaration);
+        assert_eq!(errors[2].line(), 2);
+        assert_eq!(errors[2].code(), ErrorCode::ReturnOutsideFunction);
+    }
+
+    #[test]
+    fn reject_invalid_this_and_super() {
+        assert!(resolve("print this;").is_err());
+        assert!(resolve("class A { f() { super.f(); } }").is_err());
+        assert!(resolve("class A < A {}").is_err());
+        assert!(resolve("class A { init() { return 1; } }").is_err());
+        assert!(resolve("class A {} class B < A { f() { return super.f; } }").is_ok());
+    }
+
+    #[test]
+    fn reject_loop_control_outside_loop() {
+        let errors = resolve("break;\nwhile (true) { fun f() { continue; } }").unwrap_err();
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].code(), ErrorCode::InvalidLoopControl);
+        assert_eq!(errors[1].line(), 2);
+        assert!(resolve("for (;;) { if (true) break; else { continue; } }").is_ok());
+    }
+}
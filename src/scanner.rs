@@ -1,36 +1,93 @@
 use std::collections::HashMap;
+
+use unicode_xid::UnicodeXID;
+
+use crate::error::{ErrorCode, LoxError};
 // TODO: Add lambda
 
 fn is_digit(ch: char) -> bool {
-    let uch = ch as u8;
-    (b'0'..=b'9').contains(&uch)
+    ch.is_ascii_digit()
 }
 
+/// Whether `ch` can start an identifier: `_` or any XID_Start char (UAX #31).
 fn is_alpha(ch: char) -> bool {
-    let uch = ch as u8;
-    (b'a'..=b'z').contains(&uch) || (b'A'..=b'Z').contains(&uch) || (ch == '_')
+    ch == '_' || ch.is_xid_start()
 }
 
+/// Whether `ch` can continue an identifier: any XID_Continue char.
 fn is_alphanumeric(ch: char) -> bool {
-    is_alpha(ch) || is_digit(ch)
+    ch.is_xid_continue()
+}
+
+/// Non-ASCII chars that are easily mistaken for Lox punctuation or
+/// whitespace, usually pasted in from a word processor or chat client.
+const CONFUSABLES: &[(char, &str, &str)] = &[
+    ('\u{00A0}', "No-Break Space", " "),
+    ('\u{200B}', "Zero Width Space", ""),
+    ('\u{FEFF}', "Zero Width No-Break Space", ""),
+    ('\u{2018}', "Left Single Quotation Mark", "'"),
+    ('\u{2019}', "Right Single Quotation Mark", "'"),
+    ('\u{201C}', "Left Double Quotation Mark", "\""),
+    ('\u{201D}', "Right Double Quotation Mark", "\""),
+    ('\u{2010}', "Hyphen", "-"),
+    ('\u{2013}', "En Dash", "-"),
+    ('\u{2014}', "Em Dash", "-"),
+    ('\u{2212}', "Minus Sign", "-"),
+    ('\u{2217}', "Asterisk Operator", "*"),
+    ('\u{2215}', "Division Slash", "/"),
+    ('\u{037E}', "Greek Question Mark", ";"),
+    ('\u{FF1B}', "Fullwidth Semicolon", ";"),
+    ('\u{FF08}', "Fullwidth Left Parenthesis", "("),
+    ('\u{FF09}', "Fullwidth Right Parenthesis", ")"),
+    ('\u{FF5B}', "Fullwidth Left Curly Bracket", "{"),
+    ('\u{FF5D}', "Fullwidth Right Curly Bracket", "}"),
+    ('\u{FF0C}', "Fullwidth Comma", ","),
+    ('\u{FF1D}', "Fullwidth Equals Sign", "="),
+];
+
+/// Scripts whose letters are easily mistaken for one another.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+}
+
+fn confusable_script(ch: char) -> Option<Script> {
+    match ch {
+        'a'..='z' | 'A'..='Z' | '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}' => {
+            Some(Script::Latin)
+        }
+        '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}' => Some(Script::Greek),
+        '\u{0400}'..='\u{052F}' => Some(Script::Cyrillic),
+        _ => None,
+    }
 }
 
 fn get_keywords_hashmap() -> HashMap<&'static str, TokenType> {
     HashMap::from([
         ("and", TokenType::And),
+        ("break", TokenType::Break),
+        ("catch", TokenType::Catch),
         ("class", TokenType::Class),
+        ("continue", TokenType::Continue),
+        ("div", TokenType::Div),
         ("else", TokenType::Else),
         ("false", TokenType::False),
+        ("finally", TokenType::Finally),
         ("for", TokenType::For),
         ("fun", TokenType::Fun),
         ("if", TokenType::If),
+        ("in", TokenType::In),
         ("nil", TokenType::Nil),
         ("or", TokenType::Or),
         ("print", TokenType::Print),
         ("return", TokenType::Return),
         ("super", TokenType::Super),
         ("this", TokenType::This),
+        ("throw", TokenType::Throw),
         ("true", TokenType::True),
+        ("try", TokenType::Try),
         ("var", TokenType::Var),
         ("while", TokenType::While),
     ])
@@ -39,9 +96,18 @@ fn get_keywords_hashmap() -> HashMap<&'static str, TokenType> {
 pub struct Scanner {
     source: String,
     tokens: Vec<Token>,
+    // Byte offsets into `source` of the current token's start and the next
+    // char. `current` always sits on a char boundary, so every lookahead is
+    // a constant-time slice rather than a walk from the start of the source.
     start: usize,
     current: usize,
     line: usize,
+    column: usize,
+    start_line: usize,
+    start_column: usize,
+    // Whitespace and comments seen so far, only kept for `scan_lossless`.
+    keep_trivia: bool,
+    trivia: Vec<Trivia>,
 
     keywords: HashMap<&'static str, TokenType>,
 }
@@ -54,17 +120,30 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            column: 1,
+            start_line: 1,
+            start_column: 1,
+            keep_trivia: false,
+            trivia: vec![],
             keywords: get_keywords_hashmap(),
         }
     }
 
-    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, String> {
+    pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<LoxError>> {
+        // A leading byte order mark only tells editors the encoding. It is
+        // kept as trivia but takes no column, as editors don't show it.
+        if self.current == 0 && self.source.starts_with('\u{FEFF}') {
+            self.begin_token();
+            self.current += '\u{FEFF}'.len_utf8();
+            self.add_trivia(TriviaKind::Whitespace);
+        }
+
         let mut errors = vec![];
         while !self.is_at_end() {
-            self.start = self.current;
+            self.begin_token();
             match self.scan_token() {
                 Ok(_) => (),
-                Err(msg) => errors.push(msg),
+                Err(err) => errors.push(err),
             }
         }
 
@@ -72,17 +151,67 @@ impl Scanner {
             token_type: TokenType::Eof,
             lexeme: String::from(""),
             literal: None,
-            line: self.line,
+            span: Span {
+                start: self.current,
+                end: self.current,
+                line: self.line,
+                column: self.column,
+            },
         });
 
         if errors.is_empty() {
             Ok(self.tokens.clone())
         } else {
-            Err(errors.join("\n"))
+            Err(errors)
+        }
+    }
+
+    /// Scans the source like `scan_tokens`, but keeps whitespace and comments
+    /// as trivia attached to the surrounding tokens, so that writing out every
+    /// token reproduces the source exactly. Meant for formatters and other
+    /// tooling; the parser only needs `scan_tokens`.
+    ///
+    /// Trivia after a token up to the end of its line is trailing trivia of
+    /// that token. Everything else, starting with the newline, leads the next
+    /// token; trivia at the end of the file leads the `Eof` token.
+    pub fn scan_lossless(&mut self) -> Result<Vec<LosslessToken>, Vec<LoxError>> {
+        self.keep_trivia = true;
+        let tokens = self.scan_tokens()?;
+        let mut trivia = std::mem::take(&mut self.trivia).into_iter().peekable();
+
+        let mut lossless: Vec<LosslessToken> = vec![];
+        for token in tokens {
+            if let Some(previous) = lossless.last_mut() {
+                while let Some(piece) = trivia
+                    .next_if(|t| t.span.end <= token.span.start && t.kind != TriviaKind::Newline)
+                {
+                    previous.trailing.push(piece);
+                }
+            }
+
+            let mut leading = vec![];
+            while let Some(piece) = trivia.next_if(|t| t.span.end <= token.span.start) {
+                leading.push(piece);
+            }
+
+            lossless.push(LosslessToken {
+                leading,
+                token,
+                trailing: vec![],
+            });
         }
+
+        Ok(lossless)
     }
 
-    fn scan_token(&mut self) -> Result<(), String> {
+    /// Marks the current position as the start of the next token.
+    fn begin_token(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
+    fn scan_token(&mut self) -> Result<(), LoxError> {
         let c = self.advance();
 
         match c {
@@ -90,12 +219,46 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
+            ':' => self.add_token(TokenType::Colon),
             '.' => self.add_token(TokenType::Dot),
-            '-' => self.add_token(TokenType::Minus),
-            '+' => self.add_token(TokenType::Plus),
+            '-' => {
+                if self.char_match('-') {
+                    self.add_token(TokenType::MinusMinus)
+                } else if self.char_match('=') {
+                    self.add_token(TokenType::MinusEqual)
+                } else {
+                    self.add_token(TokenType::Minus)
+                }
+            }
+            '+' => {
+                if self.char_match('+') {
+                    self.add_token(TokenType::PlusPlus)
+                } else if self.char_match('=') {
+                    self.add_token(TokenType::PlusEqual)
+                } else {
+                    self.add_token(TokenType::Plus)
+                }
+            }
             ';' => self.add_token(TokenType::Semicolon),
-            '*' => self.add_token(TokenType::Star),
+            '*' => {
+                if self.char_match('*') {
+                    self.add_token(TokenType::StarStar)
+                } else if self.char_match('=') {
+                    self.add_token(TokenType::StarEqual)
+                } else {
+                    self.add_token(TokenType::Star)
+                }
+            }
+            '%' => {
+                if self.char_match('=') {
+                    self.add_token(TokenType::PercentEqual)
+                } else {
+                    self.add_token(TokenType::Percent)
+                }
+            }
             '!' => {
                 if self.char_match('=') {
                     self.add_token(TokenType::BangEqual)
@@ -110,41 +273,86 @@ impl Scanner {
                     self.add_token(TokenType::Equal)
                 }
             }
+            '&' => self.add_token(TokenType::Ampersand),
+            '|' => self.add_token(TokenType::Pipe),
+            '^' => self.add_token(TokenType::Caret),
+            '~' => self.add_token(TokenType::Tilde),
             '<' => {
-                if self.char_match('=') {
+                if self.char_match('<') {
+                    self.add_token(TokenType::LessLess)
+                } else if self.char_match('=') {
                     self.add_token(TokenType::LessEqual)
                 } else {
                     self.add_token(TokenType::Less)
                 }
             }
             '>' => {
-                if self.char_match('=') {
+                if self.char_match('>') {
+                    self.add_token(TokenType::GreaterGreater)
+                } else if self.char_match('=') {
                     self.add_token(TokenType::GreaterEqual)
                 } else {
                     self.add_token(TokenType::Greater)
                 }
             }
+            '?' => {
+                if self.char_match('?') {
+                    self.add_token(TokenType::QuestionQuestion)
+                } else if self.char_match('.') {
+                    self.add_token(TokenType::QuestionDot)
+                } else {
+                    self.add_token(TokenType::Question)
+                }
+            }
             '/' => {
                 if self.char_match('/') {
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    self.add_trivia(TriviaKind::LineComment);
                 } else if self.char_match('*') {
-                    todo!("Add support for multiline comments")
+                    self.block_comment()?;
+                    self.add_trivia(TriviaKind::BlockComment);
+                } else if self.char_match('=') {
+                    self.add_token(TokenType::SlashEqual)
                 } else {
                     self.add_token(TokenType::Slash)
                 }
             }
-            ' ' | '\r' | '\t' => {}
-            '\n' => self.line += 1,
+            ' ' | '\r' | '\t' => self.add_trivia(TriviaKind::Whitespace),
+            '\n' => {
+                self.add_trivia(TriviaKind::Newline);
+                self.new_line();
+            }
             '"' => self.string()?,
             c => {
                 if is_digit(c) {
                     self.number()?
                 } else if is_alpha(c) {
-                    self.identifier()
+                    self.identifier()?
+                } else if let Some((_, name, ascii)) = CONFUSABLES
+                    .iter()
+                    .find(|(confusable, _, _)| *confusable == c)
+                {
+                    let help = if ascii.is_empty() {
+                        "remove it; it is invisible in most editors".to_string()
+                    } else {
+                        format!("replace it with '{ascii}'")
+                    };
+                    return Err(self
+                        .error(
+                            ErrorCode::ConfusableCharacter,
+                            &format!(
+                                "Unicode character '{c}' (U+{:04X}, {name}) looks like ASCII but is not valid here",
+                                c as u32
+                            ),
+                        )
+                        .with_help(&help));
                 } else {
-                    return Err(format!("Unrecognized char at line {}: {}", self.line, c));
+                    return Err(self.error(
+                        ErrorCode::UnexpectedCharacter,
+                        &format!("Unrecognized char: {c}"),
+                    ));
                 }
             }
         }
@@ -152,13 +360,33 @@ impl Scanner {
         Ok(())
     }
 
+    fn new_line(&mut self) {
+        self.line += 1;
+        self.column = 1;
+    }
+
+    /// The span of the token currently being scanned.
+    fn current_span(&self) -> Span {
+        Span {
+            start: self.start,
+            end: self.current,
+            line: self.start_line,
+            column: self.start_column,
+        }
+    }
+
+    fn error(&self, code: ErrorCode, message: &str) -> LoxError {
+        LoxError::scan(code, self.current_span(), message)
+    }
+
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
     }
 
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8();
+        self.column += 1;
 
         c
     }
@@ -174,101 +402,457 @@ impl Scanner {
             token_type,
             lexeme: text,
             literal,
-            line: self.line,
+            span: self.current_span(),
         })
     }
 
+    /// Records the text scanned for the current token as trivia, merging
+    /// runs of whitespace into one piece.
+    fn add_trivia(&mut self, kind: TriviaKind) {
+        if !self.keep_trivia {
+            return;
+        }
+
+        let span = self.current_span();
+        if let Some(last) = self.trivia.last_mut() {
+            if kind == TriviaKind::Whitespace
+                && last.kind == TriviaKind::Whitespace
+                && last.span.end == span.start
+            {
+                last.text.push_str(&self.source[span.start..span.end]);
+                last.span.end = span.end;
+                return;
+            }
+        }
+
+        self.trivia.push(Trivia {
+            kind,
+            text: self.source[span.start..span.end].to_string(),
+            span,
+        });
+    }
+
     fn char_match(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
         }
-        if self.source.chars().nth(self.current).unwrap() != expected {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.advance();
         true
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            '\0'
-        } else {
-            self.source.chars().nth(self.current).unwrap()
-        }
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current + 1).unwrap()
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
     }
 
-    fn string(&mut self) -> Result<(), String> {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+    fn string(&mut self) -> Result<(), LoxError> {
+        let mut value = String::new();
+        // Keep scanning past a bad escape so the rest of the string is not
+        // mistaken for code; only the first bad escape is reported.
+        let mut escape_error = None;
+
+        loop {
+            while self.peek() != '"' && !self.is_at_end() {
+                if self.peek() == '$' && self.peek_next() == '{' {
+                    break;
+                }
+
+                match self.advance() {
+                    '\n' => {
+                        value.push('\n');
+                        self.new_line();
+                    }
+                    '\\' => match self.escape() {
+                        Ok(c) => value.push(c),
+                        Err(err) => {
+                            escape_error.get_or_insert(err);
+                        }
+                    },
+                    c => value.push(c),
+                }
+            }
+
+            if self.peek() != '$' {
+                break;
             }
+
+            // The text so far becomes its own segment, followed by the tokens
+            // of the embedded expression. The string then resumes at the `}`.
             self.advance();
+            self.advance();
+            self.add_token_lit(
+                TokenType::Interpolation,
+                Some(TokenLiteral::StringValue(std::mem::take(&mut value))),
+            );
+            self.interpolation()?;
         }
 
         if self.is_at_end() {
-            return Err("Unterminated string".to_string());
+            return Err(self
+                .error(ErrorCode::UnterminatedString, "Unterminated string")
+                .with_help("add a closing '\"' to end the string"));
         }
 
         self.advance();
 
-        let value = &self.source[self.start + 1..self.current - 1];
+        if let Some(err) = escape_error {
+            return Err(err);
+        }
 
-        self.add_token_lit(
-            TokenType::StringLit,
-            Some(TokenLiteral::StringValue(value.to_string())),
-        );
+        self.add_token_lit(TokenType::StringLit, Some(TokenLiteral::StringValue(value)));
+
+        Ok(())
+    }
+
+    /// Scans the tokens of an expression embedded with `${`, stopping once
+    /// the matching `}` is consumed. That `}` starts the next string segment.
+    fn interpolation(&mut self) -> Result<(), LoxError> {
+        let opening = Span {
+            start: self.current - 2,
+            end: self.current,
+            line: self.line,
+            column: self.column - 2,
+        };
+        let mut depth = 0;
+
+        loop {
+            if self.is_at_end() {
+                return Err(LoxError::scan(
+                    ErrorCode::UnterminatedInterpolation,
+                    opening,
+                    "Unterminated interpolation",
+                )
+                .with_help("add a '}' to close the embedded expression"));
+            }
+
+            match self.peek() {
+                '}' if depth == 0 => break,
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+
+            self.begin_token();
+            self.scan_token()?;
+        }
+
+        self.begin_token();
+        self.advance();
+        Ok(())
+    }
+
+    /// Decodes the escape sequence after a `\\` that was just consumed.
+    fn escape(&mut self) -> Result<char, LoxError> {
+        let start = Span {
+            start: self.current - 1,
+            end: self.current,
+            line: self.line,
+            column: self.column - 1,
+        };
+        let error = |scanner: &Self, message: &str| {
+            let span = Span {
+                end: scanner.current,
+                ..start
+            };
+            LoxError::scan(ErrorCode::InvalidEscape, span, message)
+                .with_help("valid escapes are \\n \\t \\r \\\\ \\\" \\0 \\$ and \\u{...}")
+        };
+
+        if self.is_at_end() || self.peek() == '\n' {
+            return Err(error(self, "Unfinished escape sequence"));
+        }
+
+        match self.advance() {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '0' => Ok('\0'),
+            '$' => Ok('$'),
+            'u' => {
+                if !self.char_match('{') {
+                    return Err(error(self, "Expected '{' after '\\u'"));
+                }
+
+                let digits_start = self.current;
+                while self.peek().is_ascii_hexdigit() {
+                    self.advance();
+                }
+                let digits = self.source[digits_start..self.current].to_string();
+
+                if !self.char_match('}') {
+                    return Err(error(self, "Expected hex digits and '}' in unicode escape"));
+                }
+                if digits.is_empty() || digits.len() > 6 {
+                    return Err(error(self, "Unicode escape must have 1 to 6 hex digits"));
+                }
+
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| {
+                        error(
+                            self,
+                            &format!("'{digits}' is not a valid unicode scalar value"),
+                        )
+                    })
+            }
+            other => Err(error(self, &format!("Unknown escape sequence '\\{other}'"))),
+        }
+    }
+
+    /// Skips a `/* ... */` comment whose opening `/*` was just consumed.
+    /// Comments nest, so every `/*` inside needs its own `*/`.
+    fn block_comment(&mut self) -> Result<(), LoxError> {
+        let mut depth = 1;
+
+        while depth > 0 {
+            if self.is_at_end() {
+                let opening = Span {
+                    end: self.start + 2,
+                    ..self.current_span()
+                };
+                return Err(LoxError::scan(
+                    ErrorCode::UnterminatedComment,
+                    opening,
+                    "Unterminated comment",
+                )
+                .with_help("add a closing '*/' for every '/*'"));
+            }
+
+            match self.advance() {
+                '\n' => self.new_line(),
+                '/' if self.char_match('*') => depth += 1,
+                '*' if self.char_match('/') => depth -= 1,
+                _ => {}
+            }
+        }
 
         Ok(())
     }
 
-    fn number(&mut self) -> Result<(), String> {
-        while is_digit(self.peek()) {
+    /// Scans a number literal whose first digit was just consumed: decimal
+    /// with an optional fraction and exponent, or `0x`, `0b` and `0o` integers.
+    fn number(&mut self) -> Result<(), LoxError> {
+        let first = &self.source[self.start..self.current];
+        let radix = match (first, self.peek()) {
+            ("0", 'x' | 'X') => Some((16, "hex")),
+            ("0", 'b' | 'B') => Some((2, "binary")),
+            ("0", 'o' | 'O') => Some((8, "octal")),
+            _ => None,
+        };
+        if let Some((radix, name)) = radix {
             self.advance();
+            return self.radix_number(radix, name);
         }
 
+        let mut text = first.to_string();
+        text.push_str(&self.digits(10, true)?);
+        let mut is_float = false;
+
         if self.peek() == '.' && is_digit(self.peek_next()) {
-            self.advance();
+            is_float = true;
+            text.push(self.advance());
+            text.push_str(&self.digits(10, false)?);
+        }
 
-            while is_digit(self.peek()) {
-                self.advance();
+        let next = self.peek_next();
+        if matches!(self.peek(), 'e' | 'E') && (is_digit(next) || next == '+' || next == '-') {
+            is_float = true;
+            text.push(self.advance());
+            if matches!(self.peek(), '+' | '-') {
+                text.push(self.advance());
             }
+
+            let exponent = self.digits(10, false)?;
+            if exponent.is_empty() {
+                return Err(self.error(
+                    ErrorCode::InvalidNumber,
+                    "Expected digits in the exponent of a number literal",
+                ));
+            }
+            text.push_str(&exponent);
+        }
+
+        if !is_float {
+            let value = text.parse::<i64>().map_err(|_| {
+                self.error(
+                    ErrorCode::InvalidNumber,
+                    &format!(
+                        "Integer literal is too large: {}",
+                        &self.source[self.start..self.current]
+                    ),
+                )
+                .with_help("integers must fit in 64 bits; add '.0' to make it a float")
+            })?;
+            self.add_token_lit(TokenType::Number, Some(TokenLiteral::IValue(value)));
+            return Ok(());
         }
-        let substring = &self.source[self.start..self.current];
-        let value = substring.parse::<f64>();
 
-        match value {
+        match text.parse::<f64>() {
             Ok(value) => self.add_token_lit(TokenType::Number, Some(TokenLiteral::FValue(value))),
-            Err(_) => return Err(format!("Could not parse number: {substring}")),
+            Err(_) => {
+                return Err(self.error(
+                    ErrorCode::InvalidNumber,
+                    &format!(
+                        "Could not parse number: {}",
+                        &self.source[self.start..self.current]
+                    ),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Scans the digits of a `0x`, `0b` or `0o` literal after its prefix.
+    /// These spell out bits, so any 64-bit pattern is accepted and read as
+    /// two's complement: `0xFFFFFFFFFFFFFFFF` is -1.
+    fn radix_number(&mut self, radix: u32, name: &str) -> Result<(), LoxError> {
+        let digits = self.digits(radix, false)?;
+
+        if is_alphanumeric(self.peek()) {
+            while is_alphanumeric(self.peek()) {
+                self.advance();
+            }
+            return Err(self.error(
+                ErrorCode::InvalidNumber,
+                &format!(
+                    "Invalid digit in {name} literal: {}",
+                    &self.source[self.start..self.current]
+                ),
+            ));
+        }
+
+        if digits.is_empty() {
+            return Err(self.error(
+                ErrorCode::InvalidNumber,
+                &format!(
+                    "Expected {name} digits after '{}'",
+                    &self.source[self.start..self.current]
+                ),
+            ));
+        }
+
+        match u64::from_str_radix(&digits, radix) {
+            Ok(bits) => {
+                self.add_token_lit(TokenType::Number, Some(TokenLiteral::IValue(bits as i64)))
+            }
+            Err(_) => {
+                return Err(self
+                    .error(
+                        ErrorCode::InvalidNumber,
+                        &format!(
+                            "Number literal is too large: {}",
+                            &self.source[self.start..self.current]
+                        ),
+                    )
+                    .with_help(&format!("{name} literals can have at most 64 bits")))
+            }
         }
 
         Ok(())
     }
 
-    fn identifier(&mut self) {
+    /// Consumes a run of digits in `radix`, returning them without the `_`
+    /// separators. A separator must sit between two digits; `after_digit`
+    /// is set when the run continues a digit that was already consumed.
+    fn digits(&mut self, radix: u32, after_digit: bool) -> Result<String, LoxError> {
+        let mut digits = String::new();
+        let mut previous_was_digit = after_digit;
+        let mut misplaced_separator = false;
+
+        while self.peek().is_digit(radix) || self.peek() == '_' {
+            let c = self.advance();
+            if c == '_' {
+                misplaced_separator |= !previous_was_digit;
+                previous_was_digit = false;
+            } else {
+                digits.push(c);
+                previous_was_digit = true;
+            }
+        }
+        misplaced_separator |= self.source[..self.current].ends_with('_');
+
+        if misplaced_separator {
+            return Err(self
+                .error(
+                    ErrorCode::InvalidNumber,
+                    &format!(
+                        "Misplaced digit separator in number literal: {}",
+                        &self.source[self.start..self.current]
+                    ),
+                )
+                .with_help("'_' may only appear between two digits"));
+        }
+
+        Ok(digits)
+    }
+
+    fn identifier(&mut self) -> Result<(), LoxError> {
         while is_alphanumeric(self.peek()) {
             self.advance();
         }
 
         let substring = &self.source[self.start..self.current];
 
+        // Mixing lookalike scripts lets two names that read the same differ,
+        // as with a Cyrillic 'а' inside an otherwise Latin name.
+        let mut scripts = substring.chars().filter_map(confusable_script);
+        if let Some(first) = scripts.next() {
+            if let Some(other) = scripts.find(|script| *script != first) {
+                return Err(self
+                    .error(
+                        ErrorCode::MixedScriptIdentifier,
+                        &format!("Identifier '{substring}' mixes {first:?} and {other:?} letters"),
+                    )
+                    .with_help("write each identifier in a single script"));
+            }
+        }
+
         if let Some(&t_type) = self.keywords.get(substring) {
             self.add_token(t_type);
         } else {
             self.add_token(TokenType::Identifier);
         }
+
+        Ok(())
+    }
+}
+
+/// A region of source text. `start` and `end` are byte offsets into the
+/// source, `line` and `column` are 1-based and `column` counts chars.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns a span covering both `self` and `other`, positioned at `self`.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end.max(self.end),
+            line: self.line,
+            column: self.column,
+        }
     }
 }
 
 #[derive(Debug, Clone)]
 pub enum TokenLiteral {
+    IValue(i64),
     FValue(f64),
     StringValue(String),
 }
@@ -278,7 +862,7 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub literal: Option<TokenLiteral>,
-    pub line: usize,
+    pub span: Span,
 }
 
 impl Token {
@@ -286,7 +870,7 @@ impl Token {
         Self {
             lexeme: name.to_string(),
             token_type: TokenType::Identifier,
-            line: 0,
+            span: Span::default(),
             literal: None,
         }
     }
@@ -298,6 +882,45 @@ impl std::fmt::Display for Token {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    BlockComment,
+}
+
+/// Source text that carries no meaning for the parser.
+#[derive(Debug, Clone)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub span: Span,
+}
+
+/// A token together with the trivia around it, produced by
+/// `Scanner::scan_lossless`.
+#[derive(Debug, Clone)]
+pub struct LosslessToken {
+    pub leading: Vec<Trivia>,
+    pub token: Token,
+    pub trailing: Vec<Trivia>,
+}
+
+/// Writes the token back out exactly as it appeared in the source.
+impl std::fmt::Display for LosslessToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for trivia in &self.leading {
+            write!(f, "{}", trivia.text)?;
+        }
+        write!(f, "{}", self.token.lexeme)?;
+        for trivia in &self.trailing {
+            write!(f, "{}", trivia.text)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub enum TokenType {
     // Single-character tokens.
@@ -305,13 +928,21 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
     Comma,
+    Colon,
     Dot,
     Minus,
+    Percent,
     Plus,
     Semicolon,
     Slash,
     Star,
+    Ampersand,
+    Caret,
+    Pipe,
+    Tilde,
 
     // One or two character tokens.
     Bang,
@@ -320,29 +951,52 @@ pub enum TokenType {
     EqualEqual,
     Greater,
     GreaterEqual,
+    GreaterGreater,
     Less,
     LessEqual,
+    LessLess,
+    MinusEqual,
+    MinusMinus,
+    PercentEqual,
+    PlusEqual,
+    PlusPlus,
+    SlashEqual,
+    StarEqual,
+    StarStar,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
 
     // Literals
     Identifier,
     StringLit,
+    // A string segment that ends in `${`, followed by an embedded expression.
+    Interpolation,
     Number,
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
+    Div,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    In,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -465,7 +1119,7 @@ mod tests {
 
         assert_eq!(scanner.tokens[2].token_type, TokenType::Number);
         match scanner.tokens[2].literal {
-            Some(TokenLiteral::FValue(x)) => assert_eq!(x, 8.0),
+            Some(TokenLiteral::IValue(x)) => assert_eq!(x, 8),
             _ => panic!("Incorrect literal type"),
         }
 
@@ -509,4 +1163,312 @@ mod tests {
         assert_eq!(scanner.tokens[11].token_type, TokenType::Semicolon);
         assert_eq!(scanner.tokens[12].token_type, TokenType::Eof);
     }
+
+    #[test]
+    fn scan_error_reports_position() {
+        let source = "var a = 1;\nvar b = @;";
+        let mut scanner = Scanner::new(source);
+        let errors = scanner.scan_tokens().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), ErrorCode::UnexpectedCharacter);
+        assert_eq!(errors[0].line(), 2);
+        assert_eq!(errors[0].column(), 9);
+        assert_eq!(errors[0].range(), 19..20);
+    }
+
+    #[test]
+    fn scan_multibyte_source_spans() {
+        let source = "\"héllo\" + x;\n  \"日本\" @";
+        let mut scanner = Scanner::new(source);
+        let errors = scanner.scan_tokens().unwrap_err();
+
+        let tokens = &scanner.tokens;
+        assert_eq!(tokens[0].lexeme, "\"héllo\"");
+        assert_eq!(tokens[0].span.start..tokens[0].span.end, 0..8);
+        assert_eq!(tokens[1].span.column, 9);
+        assert_eq!(tokens[2].span.start..tokens[2].span.end, 11..12);
+        assert_eq!(tokens[4].lexeme, "\"日本\"");
+        assert_eq!(tokens[4].span.line, 2);
+        assert_eq!(tokens[4].span.column, 3);
+        assert_eq!(errors[0].column(), 8);
+        assert_eq!(errors[0].range(), 25..26);
+    }
+
+    #[test]
+    fn scan_nested_block_comments() {
+        let source = "1 /* outer /* inner\n */ still\n comment */ 2";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        assert_eq!(scanner.tokens.len(), 3);
+        assert_eq!(scanner.tokens[0].lexeme, "1");
+        assert_eq!(scanner.tokens[1].lexeme, "2");
+        assert_eq!(scanner.tokens[1].span.line, 3);
+    }
+
+    #[test]
+    fn scan_unterminated_block_comment() {
+        let source = "var a;\n  /* /* */\n";
+        let mut scanner = Scanner::new(source);
+        let errors = scanner.scan_tokens().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), ErrorCode::UnterminatedComment);
+        assert_eq!(errors[0].line(), 2);
+        assert_eq!(errors[0].column(), 3);
+        assert_eq!(errors[0].range(), 9..11);
+    }
+
+    #[test]
+    fn scan_string_escapes() {
+        let source = r#""a\tb\n\"q\" \\ \0 \u{1F600}\u{e9}""#;
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        assert_eq!(scanner.tokens[0].lexeme, source);
+        match scanner.tokens[0].literal.as_ref().unwrap() {
+            TokenLiteral::StringValue(s) => assert_eq!(s, "a\tb\n\"q\" \\ \0 \u{1F600}\u{e9}"),
+            _ => panic!("Incorrect LiteralValue type"),
+        }
+    }
+
+    #[test]
+    fn scan_invalid_escapes_report_column() {
+        let cases = [
+            (r#"print "ab\q";"#, 10, 9..11),
+            (r#"print "\u{110000}";"#, 8, 7..17),
+            (r#"print "\u{zz}";"#, 8, 7..10),
+            (r#"print "\u1234";"#, 8, 7..9),
+        ];
+
+        for (source, column, range) in cases {
+            let mut scanner = Scanner::new(source);
+            let errors = scanner.scan_tokens().unwrap_err();
+
+            assert_eq!(errors.len(), 1, "{source}");
+            assert_eq!(errors[0].code(), ErrorCode::InvalidEscape);
+            assert_eq!(errors[0].column(), column, "{source}");
+            assert_eq!(errors[0].range(), range, "{source}");
+        }
+    }
+
+    #[test]
+    fn scan_interpolated_string() {
+        let source = r#""a ${x + "}"} b ${ {"{"} } c""#;
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        let types: Vec<TokenType> = scanner.tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                TokenType::Interpolation,
+                TokenType::Identifier,
+                TokenType::Plus,
+                TokenType::StringLit,
+                TokenType::Interpolation,
+                TokenType::LeftBrace,
+                TokenType::StringLit,
+                TokenType::RightBrace,
+                TokenType::StringLit,
+                TokenType::Eof,
+            ]
+        );
+        assert_eq!(scanner.tokens[0].lexeme, "\"a ${");
+        assert_eq!(scanner.tokens[4].lexeme, "} b ${");
+        assert_eq!(scanner.tokens[8].lexeme, "} c\"");
+        match scanner.tokens[8].literal.as_ref().unwrap() {
+            TokenLiteral::StringValue(s) => assert_eq!(s, " c"),
+            _ => panic!("Incorrect LiteralValue type"),
+        }
+    }
+
+    #[test]
+    fn scan_unterminated_interpolation() {
+        let mut scanner = Scanner::new("print \"a ${b");
+        let errors = scanner.scan_tokens().unwrap_err();
+
+        assert_eq!(errors[0].code(), ErrorCode::UnterminatedInterpolation);
+        assert_eq!(errors[0].column(), 10);
+    }
+
+    #[test]
+    fn scan_unicode_identifiers() {
+        let source = "var größe = 1; var 名前 = größe; _x9 café";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        let identifiers: Vec<&str> = scanner
+            .tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::Identifier)
+            .map(|t| t.lexeme.as_str())
+            .collect();
+        assert_eq!(identifiers, vec!["größe", "名前", "größe", "_x9", "café"]);
+    }
+
+    #[test]
+    fn scan_keywords_stay_ascii() {
+        // Fullwidth letters are valid identifier chars but never keywords.
+        let source = "ｖａｒ";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        assert_eq!(scanner.tokens[0].token_type, TokenType::Identifier);
+    }
+
+    #[test]
+    fn scan_rejects_confusable_and_invalid_chars() {
+        let mut scanner = Scanner::new("print 1\u{FF1B}");
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::ConfusableCharacter);
+        assert_eq!(errors[0].column(), 8);
+
+        let mut scanner = Scanner::new("var a€ = 1;");
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::UnexpectedCharacter);
+        assert_eq!(errors[0].column(), 6);
+    }
+
+    #[test]
+    fn scan_rejects_mixed_script_identifiers() {
+        // The second 'а' is Cyrillic.
+        let mut scanner = Scanner::new("var pаss = 1;");
+        let errors = scanner.scan_tokens().unwrap_err();
+        assert_eq!(errors[0].code(), ErrorCode::MixedScriptIdentifier);
+        assert_eq!(errors[0].range(), 4..9);
+
+        let mut scanner = Scanner::new("var λx;");
+        assert!(scanner.scan_tokens().is_err());
+        let mut scanner = Scanner::new("var größe_2 = 1; var 名前x = größe_2;");
+        assert!(scanner.scan_tokens().is_ok());
+    }
+
+    #[test]
+    fn scan_skips_leading_byte_order_mark() {
+        let source = "\u{FEFF}var a;";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+        assert_eq!(scanner.tokens[0].token_type, TokenType::Var);
+        assert_eq!(scanner.tokens[0].span.column, 1);
+
+        let mut scanner = Scanner::new(source);
+        let text: String = scanner
+            .scan_lossless()
+            .unwrap()
+            .iter()
+            .map(|t| t.to_string())
+            .collect();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn scan_extended_number_literals() {
+        let source = "0xFF 0b1010 0o755 1e-9 6.02E23 1_000_000 0xdead_BEEF 2.5e+3";
+        let mut scanner = Scanner::new(source);
+        scanner.scan_tokens().unwrap();
+
+        let values: Vec<String> = scanner
+            .tokens
+            .iter()
+            .filter_map(|t| match t.literal {
+                Some(TokenLiteral::IValue(x)) => Some(format!("int {x}")),
+                Some(TokenLiteral::FValue(x)) => Some(format!("float {x:?}")),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                "int 255",
+                "int 10",
+                "int 493",
+                "float 1e-9",
+                "float 6.02e23",
+                "int 1000000",
+                "int 3735928559",
+                "float 2500.0"
+            ]
+        );
+        assert_eq!(scanner.tokens[5].lexeme, "1_000_000");
+    }
+
+    #[test]
+    fn scan_malformed_number_literals() {
+        for source in [
+            "0x",
+            "0b",
+            "1__0",
+            "1_",
+            "0b102",
+            "0xFG",
+            "1e+",
+            "0x_1",
+            "0x1_0000_0000_0000_0000",
+        ] {
+            let mut scanner = Scanner::new(source);
+            let errors = scanner.scan_tokens().unwrap_err();
+
+            assert_eq!(errors.len(), 1, "{source}");
+            assert_eq!(errors[0].code(), ErrorCode::InvalidNumber, "{source}");
+            assert_eq!(errors[0].range(), 0..source.len(), "{source}");
+        }
+    }
+
+    #[test]
+    fn scan_radix_literals_as_bit_patterns() {
+        let mut scanner =
+            Scanner::new("0xFFFFFFFFFFFFFFFF 0x8000000000000000 0o1777777777777777777777");
+        scanner.scan_tokens().unwrap();
+
+        let values: Vec<i64> = scanner
+            .tokens
+            .iter()
+            .filter_map(|t| match t.literal {
+                Some(TokenLiteral::IValue(x)) => Some(x),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(values, vec![-1, i64::MIN, -1]);
+    }
+
+    #[test]
+    fn scan_lossless_round_trips_source() {
+        let source = "// header\n\nvar  a = 1; // trailing\n/* block\n  comment */\r\n\tprint \"${ a }é\" ;\n  ";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_lossless().unwrap();
+
+        let text: String = tokens.iter().map(|t| t.to_string()).collect();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn scan_lossless_attaches_trivia() {
+        let source = "// doc\nvar a; // note\n";
+        let mut scanner = Scanner::new(source);
+        let tokens = scanner.scan_lossless().unwrap();
+
+        let var = &tokens[0];
+        assert_eq!(var.token.token_type, TokenType::Var);
+        let leading: Vec<TriviaKind> = var.leading.iter().map(|t| t.kind).collect();
+        assert_eq!(leading, vec![TriviaKind::LineComment, TriviaKind::Newline]);
+
+        let semicolon = &tokens[2];
+        let trailing: Vec<&str> = semicolon.trailing.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(trailing, vec![" ", "// note"]);
+
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.token.token_type, TokenType::Eof);
+        assert_eq!(eof.leading[0].kind, TriviaKind::Newline);
+    }
+
+    #[test]
+    fn scan_tokens_drops_trivia() {
+        let mut scanner = Scanner::new("  a // c\n");
+        let tokens = scanner.scan_tokens().unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert!(scanner.trivia.is_empty());
+    }
 }
@@ -1,50 +1,142 @@
+use std::rc::Rc;
+
 use crate::expression::Expr;
-use crate::scanner::Token;
+use crate::scanner::{Span, Token};
 
 #[derive(Clone)]
 pub enum Stmt {
     Block {
         statements: Vec<Stmt>,
+        span: Span,
+    },
+    Break {
+        keyword: Token,
+        span: Span,
+    },
+    Class {
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Stmt>,
+        span: Span,
+    },
+    Continue {
+        keyword: Token,
+        span: Span,
     },
     Expression {
         expression: Expr,
+        span: Span,
+    },
+    /// A `for (name in iterable)` loop. `keyword` is the `in` token, used to
+    /// locate errors about the iterable.
+    ForIn {
+        name: Token,
+        keyword: Token,
+        iterable: Expr,
+        body: Box<Stmt>,
+        span: Span,
     },
     Function {
         name: Token,
         params: Vec<Token>,
-        body: Vec<Stmt>,
+        body: Rc<Vec<Stmt>>,
+        span: Span,
     },
     If {
         condition: Expr,
         then_stmt: Box<Stmt>,
         else_stmt: Option<Box<Stmt>>,
+        span: Span,
     },
     Print {
         expression: Expr,
+        span: Span,
     },
     Return {
         keyword: Token,
         value: Option<Expr>,
+        span: Span,
+    },
+    Throw {
+        keyword: Token,
+        value: Expr,
+        span: Span,
+    },
+    /// A `try` block with an optional `catch (name)` handler and an optional
+    /// `finally` block; at least one of the two is present.
+    Try {
+        body: Box<Stmt>,
+        catch: Option<(Token, Box<Stmt>)>,
+        finally: Option<Box<Stmt>>,
+        span: Span,
     },
     Var {
         name: Token,
         initializer: Expr,
+        span: Span,
     },
     While {
         condition: Expr,
         body: Box<Stmt>,
+        /// The increment clause of a desugared `for` loop. It runs after the
+        /// body even when the body ends with `continue`.
+        increment: Option<Expr>,
+        span: Span,
     },
 }
 
+impl Stmt {
+    /// Returns the source range this statement was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Block { span, .. }
+            | Stmt::Break { span, .. }
+            | Stmt::Class { span, .. }
+            | Stmt::Continue { span, .. }
+            | Stmt::Expression { span, .. }
+            | Stmt::ForIn { span, .. }
+            | Stmt::Function { span, .. }
+            | Stmt::If { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::Throw { span, .. }
+            | Stmt::Try { span, .. }
+            | Stmt::Var { span, .. }
+            | Stmt::While { span, .. } => *span,
+        }
+    }
+}
+
 impl std::fmt::Display for Stmt {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
-            Stmt::Block { statements } => format!(
+            Stmt::Block { statements, .. } => format!(
                 "(block {})",
                 statements.iter().map(|s| s.to_string()).collect::<String>()
             ),
-            Stmt::Expression { expression } => expression.to_string(),
-            Stmt::Function { name, params, body } => {
+            Stmt::Break { .. } => String::from("(break)"),
+            Stmt::Class {
+                name,
+                superclass,
+                methods,
+                ..
+            } => match superclass {
+                Some(superclass) => {
+                    format!("(class {} < {superclass} {methods:?})", name.lexeme)
+                }
+                None => format!("(class {} {methods:?})", name.lexeme),
+            },
+            Stmt::Continue { .. } => String::from("(continue)"),
+            Stmt::Expression { expression, .. } => expression.to_string(),
+            Stmt::ForIn {
+                name,
+                iterable,
+                body,
+                ..
+            } => format!("(for {} in {iterable} do {body})", name.lexeme),
+            Stmt::Function {
+                name, params, body, ..
+            } => {
                 let param_names = params
                     .iter()
                     .map(|p| p.lexeme.clone())
@@ -56,21 +148,49 @@ impl std::fmt::Display for Stmt {
                 condition,
                 then_stmt,
                 else_stmt,
+                ..
             } => match else_stmt {
                 Some(else_stmt) => {
                     format!("(if {condition} then {then_stmt} else {else_stmt})")
                 }
                 None => format!("(if {condition} then {then_stmt})"),
             },
-            Stmt::Print { expression } => format!("(print {expression})"),
-            Stmt::Return { keyword: _, value } => match value {
+            Stmt::Print { expression, .. } => format!("(print {expression})"),
+            Stmt::Return {
+                keyword: _, value, ..
+            } => match value {
                 Some(expr) => format!("(-> {expr})"),
                 None => String::from("(-> nil)"),
             },
-            Stmt::Var { name, initializer } => format!("(var {} {initializer})", name.lexeme),
-            Stmt::While { condition, body } => {
-                format!("(while {condition} do {body})")
+            Stmt::Throw { value, .. } => format!("(throw {value})"),
+            Stmt::Try {
+                body,
+                catch,
+                finally,
+                ..
+            } => {
+                let mut s = format!("(try {body}");
+                if let Some((name, handler)) = catch {
+                    s.push_str(&format!(" catch {} {handler}", name.lexeme));
+                }
+                if let Some(finally) = finally {
+                    s.push_str(&format!(" finally {finally}"));
+                }
+                s.push(')');
+                s
             }
+            Stmt::Var {
+                name, initializer, ..
+            } => format!("(var {} {initializer})", name.lexeme),
+            Stmt::While {
+                condition,
+                body,
+                increment,
+                ..
+            } => match increment {
+                Some(increment) => format!("(while {condition} do {body} step {increment})"),
+                None => format!("(while {condition} do {body})"),
+            },
         };
         write!(f, "{s}")
     }